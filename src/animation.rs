@@ -0,0 +1,180 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+
+/// Every clip's frames, keyed by `Clip`.
+pub const CLIPS: &str = "bee.anim.ron";
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Clips>()
+            .init_asset_loader::<ClipsLoader>()
+            .add_event::<AnimationCue>()
+            .add_startup_system(load_clips)
+            .add_system(animate);
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Clip {
+    Idle,
+    Flap,
+    Hurt,
+    Dead,
+    Spin,
+    Buzz,
+}
+
+/// Something a frame asks the rest of the game to do when it comes up.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cue {
+    WingBurst,
+    Fall,
+    Fallen,
+}
+
+/// Sent when a frame with a cue is shown.
+pub struct AnimationCue {
+    pub entity: Entity,
+    pub cue: Cue,
+}
+
+#[derive(Deserialize, Debug)]
+struct Frame {
+    index: usize,
+    secs: f32,
+    #[serde(default)]
+    cue: Option<Cue>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ClipDef {
+    frames: Vec<Frame>,
+    #[serde(default)]
+    looping: bool,
+    /// Clip to carry on with once a one-shot ends. Without one the last
+    /// frame is held.
+    #[serde(default)]
+    then: Option<Clip>,
+}
+
+#[derive(Deserialize, TypeUuid, Debug)]
+#[uuid = "c5d1f0a2-7e3b-4a96-8b24-5f9e1d3c7a80"]
+pub struct Clips {
+    clips: HashMap<Clip, ClipDef>,
+}
+
+#[derive(Default)]
+struct ClipsLoader;
+
+impl AssetLoader for ClipsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let clips: Clips = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(clips));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct ClipsHandle(Handle<Clips>);
+
+fn load_clips(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ClipsHandle(asset_server.load(CLIPS)));
+}
+
+/// Plays clips on a `TextureAtlasSprite`.
+#[derive(Component)]
+pub struct Animator {
+    clip: Clip,
+    frame: usize,
+    timer: Timer,
+    /// Set when a frame has just been entered and not yet shown.
+    fresh: bool,
+    /// Whether the clip playing loops, known once its first frame is shown.
+    looping: bool,
+}
+
+impl Animator {
+    pub fn new(clip: Clip) -> Self {
+        Self {
+            clip,
+            frame: 0,
+            timer: Timer::default(),
+            fresh: true,
+            looping: false,
+        }
+    }
+
+    /// Starts `clip` from its first frame. A looping clip that is already
+    /// playing carries on undisturbed.
+    pub fn play(&mut self, clip: Clip) {
+        if clip == self.clip && self.looping {
+            return;
+        }
+        self.clip = clip;
+        self.frame = 0;
+        self.fresh = true;
+    }
+}
+
+fn animate(
+    mut query: Query<(Entity, &mut TextureAtlasSprite, &mut Animator)>,
+    mut cues: EventWriter<AnimationCue>,
+    handle: Res<ClipsHandle>,
+    clips: Res<Assets<Clips>>,
+    time: Res<Time>,
+) {
+    let Some(clips) = clips.get(&handle.0) else {
+        return;
+    };
+
+    for (entity, mut tas, mut anim) in query.iter_mut() {
+        if !anim.fresh && anim.timer.tick(time.delta()).finished() {
+            let Some(def) = clips.clips.get(&anim.clip) else {
+                continue;
+            };
+            if anim.frame + 1 < def.frames.len() {
+                anim.frame += 1;
+                anim.fresh = true;
+            } else if def.looping {
+                anim.frame = 0;
+                anim.fresh = true;
+            } else if let Some(then) = def.then {
+                anim.play(then);
+            }
+        }
+
+        if anim.fresh {
+            let Some(def) = clips.clips.get(&anim.clip) else {
+                continue;
+            };
+            let Some(frame) = def.frames.get(anim.frame) else {
+                continue;
+            };
+            anim.fresh = false;
+            anim.looping = def.looping;
+            anim.timer = Timer::new(Duration::from_secs_f32(frame.secs), TimerMode::Once);
+            tas.index = frame.index;
+            if let Some(cue) = frame.cue {
+                cues.send(AnimationCue { entity, cue });
+            }
+        }
+    }
+}
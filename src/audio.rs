@@ -0,0 +1,335 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioInstance, AudioTween};
+
+use crate::{bee_game::GameInfo, scores::NewHighScore, settings::Settings, GameState};
+
+const CROSSFADE: Duration = Duration::from_millis(1200);
+const STEM_FADE: Duration = Duration::from_millis(2500);
+const DUCK_LEVEL: f64 = 0.25;
+const DUCK_TIME: Duration = Duration::from_millis(1500);
+
+/// How much faster the music plays at the top difficulty.
+const MAX_SPEEDUP: f64 = 0.15;
+
+/// Playback rates of the notes in the high score stinger (a major chord).
+const STINGER_CHORD: [f64; 3] = [1.0, 1.26, 1.5];
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_channel::<Music>()
+            .add_audio_channel::<Sfx>()
+            .add_event::<PlayMusic>()
+            .add_event::<DuckMusic>()
+            .init_resource::<MusicPlayer>()
+            .add_system(switch_music(MusicTrack::Menu).in_schedule(OnEnter(GameState::Menu)))
+            .add_system(switch_music(MusicTrack::Game).in_schedule(OnEnter(GameState::Game)))
+            .add_system(music_player)
+            .add_system(music_ducking.after(music_player))
+            .add_system(
+                adaptive_music
+                    .after(music_player)
+                    .in_set(OnUpdate(GameState::Game)),
+            )
+            .add_system(high_score_stinger)
+            .add_system(apply_volumes.run_if(resource_changed::<Settings>()));
+    }
+}
+
+/// Channel for the looping background music.
+#[derive(Resource)]
+pub struct Music;
+
+/// Channel for one-shot sound effects.
+#[derive(Resource)]
+pub struct Sfx;
+
+/// One layer of a track. Every stem of a track starts together and loops
+/// in sync, staying silent until the score reaches `from_score`.
+pub struct Stem {
+    pub path: &'static str,
+    pub from_score: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MusicTrack {
+    Menu,
+    /// Also the meadow's.
+    Game,
+    Greenhouse,
+    City,
+    Hive,
+}
+
+impl MusicTrack {
+    pub const ALL: [MusicTrack; 5] = [
+        MusicTrack::Menu,
+        MusicTrack::Game,
+        MusicTrack::Greenhouse,
+        MusicTrack::City,
+        MusicTrack::Hive,
+    ];
+
+    pub fn stems(self) -> &'static [Stem] {
+        // extra layers must match the length of the recording they go over
+        const BED: Stem = Stem {
+            path: "sounds/bg.mp3",
+            from_score: 0,
+        };
+        const DRUMS: Stem = Stem {
+            path: "sounds/bg_drums.wav",
+            from_score: 10,
+        };
+        match self {
+            MusicTrack::Menu => &[BED],
+            MusicTrack::Game => &[BED, DRUMS],
+            // each biome past the meadow adds a layer of its own
+            MusicTrack::Greenhouse => &[
+                BED,
+                DRUMS,
+                Stem {
+                    path: "sounds/bg_greenhouse.wav",
+                    from_score: 0,
+                },
+            ],
+            MusicTrack::City => &[
+                BED,
+                DRUMS,
+                Stem {
+                    path: "sounds/bg_city.wav",
+                    from_score: 0,
+                },
+            ],
+            MusicTrack::Hive => &[
+                BED,
+                DRUMS,
+                Stem {
+                    path: "sounds/bg_hive.wav",
+                    from_score: 0,
+                },
+            ],
+        }
+    }
+}
+
+/// Crossfades from whatever is playing to the given track, keeping any
+/// stems the two have in common.
+pub struct PlayMusic(pub MusicTrack);
+
+/// Briefly pulls the music down so an important sound effect cuts through.
+pub struct DuckMusic;
+
+struct Layer {
+    path: &'static str,
+    instance: Handle<AudioInstance>,
+    from_score: u32,
+    audible: bool,
+}
+
+#[derive(Resource, Default)]
+struct MusicPlayer {
+    track: Option<MusicTrack>,
+    layers: Vec<Layer>,
+    duck: Option<Timer>,
+    playback_rate: f64,
+}
+
+impl MusicPlayer {
+    fn volume(&self, settings: &Settings) -> f64 {
+        match self.duck {
+            Some(_) => settings.music() * DUCK_LEVEL,
+            None => settings.music(),
+        }
+    }
+
+    fn set_volume(&self, instances: &mut Assets<AudioInstance>, volume: f64, tween: AudioTween) {
+        for layer in self.layers.iter().filter(|l| l.audible) {
+            if let Some(instance) = instances.get_mut(&layer.instance) {
+                instance.set_volume(volume, tween.clone());
+            }
+        }
+    }
+}
+
+fn switch_music(track: MusicTrack) -> impl FnMut(EventWriter<PlayMusic>) {
+    move |mut events: EventWriter<PlayMusic>| events.send(PlayMusic(track))
+}
+
+fn music_player(
+    mut events: EventReader<PlayMusic>,
+    mut player: ResMut<MusicPlayer>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    music: Res<AudioChannel<Music>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    let Some(PlayMusic(track)) = events.iter().last() else {
+        return;
+    };
+
+    if player.track == Some(*track) {
+        return;
+    }
+
+    // stems shared with the new track carry on rather than start over
+    let mut playing = Vec::new();
+    for layer in player.layers.drain(..) {
+        if track.stems().iter().any(|stem| stem.path == layer.path) {
+            playing.push(layer);
+        } else if let Some(old) = instances.get_mut(&layer.instance) {
+            old.stop(AudioTween::linear(CROSSFADE));
+        }
+    }
+
+    // new stems join in time with the ones carrying on
+    let position = playing
+        .iter()
+        .find_map(|layer| instances.get(&layer.instance)?.state().position());
+
+    let volume = player.volume(&settings);
+    player.layers = track
+        .stems()
+        .iter()
+        .map(|stem| {
+            // a stem carrying on stays as loud as it was, the score bringing
+            // it in or out as usual
+            if let Some(i) = playing.iter().position(|layer| layer.path == stem.path) {
+                let layer = playing.swap_remove(i);
+                return Layer {
+                    from_score: stem.from_score,
+                    ..layer
+                };
+            }
+
+            let audible = stem.from_score == 0;
+
+            let mut play = music.play(asset_server.load(stem.path));
+            if let Some(position) = position {
+                play.start_from(position);
+            }
+            let instance = play
+                .looped()
+                .with_volume(if audible { volume } else { 0.0 })
+                .fade_in(AudioTween::linear(CROSSFADE))
+                .handle();
+
+            Layer {
+                path: stem.path,
+                instance,
+                from_score: stem.from_score,
+                audible,
+            }
+        })
+        .collect();
+    player.track = Some(*track);
+    // a run moving between biomes keeps its pace
+    if *track == MusicTrack::Menu && player.playback_rate != 1.0 {
+        player.playback_rate = 1.0;
+        music
+            .set_playback_rate(1.0)
+            .fade_in(AudioTween::linear(CROSSFADE));
+    }
+}
+
+fn adaptive_music(
+    game_info: Res<GameInfo>,
+    mut player: ResMut<MusicPlayer>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    music: Res<AudioChannel<Music>>,
+    settings: Res<Settings>,
+) {
+    if !game_info.is_changed() {
+        return;
+    }
+
+    let volume = player.volume(&settings);
+    for layer in player.layers.iter_mut() {
+        let audible = game_info.score >= layer.from_score;
+        if layer.audible == audible {
+            continue;
+        }
+
+        layer.audible = audible;
+        if let Some(instance) = instances.get_mut(&layer.instance) {
+            instance.set_volume(
+                if audible { volume } else { 0.0 },
+                AudioTween::linear(STEM_FADE),
+            );
+        }
+    }
+
+    let rate = 1.0 + MAX_SPEEDUP * (game_info.difficulty() as f64 - 1.0);
+    if (rate - player.playback_rate).abs() > 0.005 {
+        player.playback_rate = rate;
+        music
+            .set_playback_rate(rate)
+            .fade_in(AudioTween::linear(STEM_FADE));
+    }
+}
+
+fn music_ducking(
+    mut events: EventReader<DuckMusic>,
+    mut player: ResMut<MusicPlayer>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    if events.iter().count() > 0 {
+        if player.duck.is_none() {
+            player.set_volume(
+                &mut instances,
+                settings.music() * DUCK_LEVEL,
+                AudioTween::linear(Duration::from_millis(100)),
+            );
+        }
+        player.duck = Some(Timer::new(DUCK_TIME, TimerMode::Once));
+        return;
+    }
+
+    let Some(duck) = player.duck.as_mut() else {
+        return;
+    };
+
+    if duck.tick(time.delta()).finished() {
+        player.duck = None;
+        player.set_volume(
+            &mut instances,
+            settings.music(),
+            AudioTween::linear(CROSSFADE),
+        );
+    }
+}
+
+fn high_score_stinger(
+    mut events: EventReader<NewHighScore>,
+    mut duck: EventWriter<DuckMusic>,
+    sfx: Res<AudioChannel<Sfx>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+
+    for rate in STINGER_CHORD {
+        sfx.play(asset_server.load("sounds/beep.wav"))
+            .with_volume(settings.sfx())
+            .with_playback_rate(rate);
+    }
+    duck.send(DuckMusic);
+}
+
+fn apply_volumes(
+    settings: Res<Settings>,
+    player: Res<MusicPlayer>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+) {
+    player.set_volume(
+        &mut instances,
+        player.volume(&settings),
+        AudioTween::default(),
+    );
+}
@@ -1,28 +1,70 @@
 use std::time::Duration;
 
-use bevy::{core_pipeline::bloom::BloomSettings, prelude::*};
+use bevy::{
+    core_pipeline::{bloom::BloomSettings, clear_color::ClearColorConfig},
+    prelude::*,
+    render::{camera::Viewport, view::RenderLayers},
+    sprite::Anchor,
+    window::{PrimaryWindow, WindowResized},
+};
 use bevy_prototype_debug_lines::*;
 
-use crate::GameState;
+use crate::{
+    animation::{AnimationCue, Animator, Clip, Cue},
+    audio::DuckMusic,
+    biomes::{Biome, PillarLook},
+    bosses::BossArrives,
+    camera::{CameraFx, CameraFxSet, CameraKick},
+    ghost,
+    levels::{Level, LevelRun, ObstacleKind},
+    modes::GameMode,
+    net::Session,
+    particles::{ParticleEmitter, ParticleKind, SpawnParticles},
+    scores::{HighScores, NewHighScore},
+    settings::{Controls, Settings},
+    sfx::{PlaySfx, SfxEmitter, SoundEffect},
+    sim::{
+        self, Flight, Layout, Weather, BEE_HALF_SIZE, BEE_X, FLIGHT_STEP, FLOOR, GAP_BOTTOM,
+        GAP_TOP, PILLAR_HALF_WIDTH, PILLAR_SCALE, SPAWN_X, WANDER,
+    },
+    skins::Dressed,
+    stats::{Hazard, Tally},
+    view, GameState,
+};
 
 pub struct BeeGame;
 
 impl Plugin for BeeGame {
     fn build(&self, app: &mut App) {
-        app.add_system(setup.in_schedule(OnEnter(GameState::Game)))
-            .add_system(clouds_move.in_set(OnUpdate(GameState::Game)))
+        app.insert_resource(HighScores::load())
+            .add_event::<NewHighScore>()
+            .add_event::<Flapped>()
+            .add_system(setup.in_schedule(OnEnter(GameState::Game)))
             .add_system(pillar_spawner.in_set(OnUpdate(GameState::Game)))
             .add_system(pillar_move.in_set(OnUpdate(GameState::Game)))
             .add_system(jump_input.in_set(OnUpdate(GameState::Game)))
             .add_system(let_it_jump.in_set(OnUpdate(GameState::Game)))
             .add_system(bee_fly)
+            .add_system(bee_cues.in_set(OnUpdate(GameState::Game)))
+            .add_system(bee_fall.in_set(OnUpdate(GameState::Game)))
+            .add_system(
+                bee_tilt
+                    .in_set(OnUpdate(GameState::Game))
+                    .before(collisions),
+            )
             .add_system(score_text_update.in_set(OnUpdate(GameState::Game)))
-            .add_system(anim_handler)
-            .add_startup_system(audio_setup)
-            .add_system(collisions.in_set(OnUpdate(GameState::Game)))
+            .add_system(
+                collisions
+                    .in_set(OnUpdate(GameState::Game))
+                    .before(CameraFxSet),
+            )
             //.add_system(display_colliders.in_set(OnUpdate(GameState::Game)))
+            .add_system(display_colliders.in_set(OnUpdate(GameState::Editor)))
             .add_system(game_killer.in_set(OnUpdate(GameState::Game)))
             .add_system(pillar_score.in_set(OnUpdate(GameState::Game)))
+            .add_system(resize_bounds.in_set(OnUpdate(GameState::Game)))
+            .add_system(split_viewports.in_set(OnUpdate(GameState::Game)))
+            .add_system(save_high_score.in_schedule(OnExit(GameState::Game)))
             .add_system(cleanup.in_schedule(OnExit(GameState::Game)));
     }
 }
@@ -33,93 +75,213 @@ struct BeeGameMarker;
 #[derive(Resource)]
 pub struct GameInfo {
     pub score: u32,
+    /// Counts down to the end of a timed run.
+    pub clock: Option<Timer>,
+    /// Pillars bounced off in modes without death.
+    pub bounces: u32,
+    /// What the pillar layout was generated from.
+    pub seed: u64,
     is_dead: bool,
+    beat_best: bool,
 }
 
+impl GameInfo {
+    /// Ramps from 1.0 at the start of a run up to 2.0 at 25 points.
+    pub fn difficulty(&self) -> f32 {
+        1.0 + (self.score as f32 / 25.0).min(1.0)
+    }
+
+    /// Whether the run is over, bar the falling.
+    pub fn is_dead(&self) -> bool {
+        self.is_dead
+    }
+}
+
+/// Drives the pillar layout, seeded per day in daily runs so everyone gets
+/// the same pillars, and from the ghost when racing one.
 #[derive(Resource)]
-struct PillarShared {
-    x_vel: f32,
-    y_pos: f32,
-    y_vel: f32,
+struct PillarRng(Layout);
+
+#[derive(Resource)]
+pub struct PillarShared {
+    pub x_vel: f32,
+    /// Each player gets their own copy of every pillar.
+    players: usize,
     x_pos_bounds: (f32, f32),
-    y_pos_bounds: (f32, f32),
+    gap_scale: f32,
     spawn_timer: Timer,
+    /// Pillars spawned so far this run.
+    spawned: usize,
+    /// Holds the spawner back while a boss is about.
+    hold: Option<Timer>,
     texture: Handle<Image>,
+    look: &'static PillarLook,
     prop_texture: Handle<TextureAtlas>,
 }
 
+/// A bee and the world it flies through, which each player moves up and
+/// down on their own.
+#[derive(Component)]
+pub struct Player {
+    pub index: usize,
+    pub y_pos: f32,
+    pub y_vel: f32,
+    /// Flapped since the last flight step.
+    flap: bool,
+    is_dead: bool,
+}
+
+/// A flap taking effect, `at` seconds into the run's flight.
+pub struct Flapped {
+    pub player: usize,
+    pub at: f32,
+}
+
+/// Flight steps taken this run, the time not yet stepped through, and the
+/// weather stepped along with them.
+#[derive(Resource)]
+pub struct FlightClock {
+    steps: u32,
+    owed: f32,
+    pub weather: Weather,
+}
+
+impl Player {
+    pub fn is_dead(&self) -> bool {
+        self.is_dead
+    }
+}
+
+/// What a player's camera sees in a split screen, on top of the shared
+/// scenery on layer 0.
+fn player_layer(index: usize) -> u8 {
+    index as u8 + 1
+}
+
+/// Which half of the window a versus camera draws to, from the left.
+#[derive(Component)]
+struct SplitView(usize);
+
+impl PillarShared {
+    /// Where new pillars come in from.
+    pub fn spawn_x(&self) -> f32 {
+        self.x_pos_bounds.0
+    }
+
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    /// Seconds until the pillars come back after a boss.
+    pub fn hold_left(&self) -> f32 {
+        self.hold.as_ref().map_or(0.0, Timer::remaining_secs)
+    }
+
+    /// What a player's own things are drawn on in a split screen.
+    pub fn layer(&self, player: usize) -> Option<RenderLayers> {
+        (self.players > 1).then(|| RenderLayers::layer(player_layer(player)))
+    }
+
+    /// Changes how pillars spawned from now on are drawn.
+    pub fn set_look(&mut self, look: &'static PillarLook, asset_server: &AssetServer) {
+        self.texture = asset_server.load(look.texture);
+        self.look = look;
+    }
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     wins: Query<&Window>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    settings: Res<Settings>,
+    mode: Res<GameMode>,
+    session: Option<Res<Session>>,
 ) {
+    let seed = match session {
+        Some(session) if *mode == GameMode::Online => session.seed(),
+        _ => ghost::seed(*mode, &settings),
+    };
     commands.insert_resource(GameInfo {
         score: 0,
+        clock: mode
+            .rules()
+            .time_limit
+            .map(|secs| Timer::from_seconds(secs, TimerMode::Once)),
+        bounces: 0,
+        seed,
         is_dead: false,
+        beat_best: false,
     });
 
-    commands.spawn((
-        Camera2dBundle {
-            camera: Camera {
-                hdr: true,
+    commands.insert_resource(PillarRng(Layout::new(seed)));
+    commands.insert_resource(FlightClock {
+        steps: 0,
+        owed: 0.0,
+        weather: Weather::new(seed),
+    });
+
+    let players = mode.players();
+    for index in 0..players {
+        let mut camera = commands.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    hdr: true,
+                    order: index as isize,
+                    ..default()
+                },
+                camera_2d: Camera2d {
+                    // clearing wipes the whole window, not just the viewport
+                    clear_color: match index {
+                        0 => ClearColorConfig::Custom(settings.palette.sky()),
+                        _ => ClearColorConfig::None,
+                    },
+                },
+                projection: view::projection(),
                 ..default()
             },
-            camera_2d: Camera2d {
-                clear_color: bevy::core_pipeline::clear_color::ClearColorConfig::Custom(
-                    Color::rgb(0.35, 0.8, 1.0),
-                ),
+            BloomSettings {
+                ..Default::default()
             },
-            ..default()
-        },
-        BloomSettings {
-            ..Default::default()
-        },
-        BeeGameMarker,
-    ));
-
-    commands.spawn((
-        SpriteBundle {
-            transform: Transform::from_xyz(0.0, 0.0, 10.0).with_scale(Vec3::splat(0.8)),
-            texture: asset_server.load("textures/bg2.png"),
-            ..Default::default()
-        },
-        BeeGameMarker,
-    ));
-
-    setup_scoreboard(&mut commands, &asset_server);
-
-    setup_pillars(&mut commands, &asset_server, &wins, &mut texture_atlases);
+            CameraFx::default(),
+            BeeGameMarker,
+        ));
+        if players > 1 {
+            camera.insert((
+                SplitView(index),
+                RenderLayers::layer(0).with(player_layer(index)),
+            ));
+            if index > 0 {
+                camera.insert(UiCameraConfig { show_ui: false });
+            }
+        }
+    }
 
-    setup_bee(&mut commands, &asset_server, &mut texture_atlases);
+    setup_scoreboard(&mut commands, &asset_server, &settings);
 
-    setup_clouds(&mut commands, &asset_server, &wins);
+    setup_pillars(
+        &mut commands,
+        &asset_server,
+        &wins,
+        &mut texture_atlases,
+        *mode,
+    );
 
-    setup_babees(&mut commands, &asset_server, &wins);
+    for index in 0..players {
+        setup_bee(&mut commands, &settings, index, players);
+    }
 }
 
 #[derive(Component)]
 struct ScoreText;
 
-fn setup_scoreboard(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+fn setup_scoreboard(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    settings: &Res<Settings>,
+) {
     commands.spawn((
-        TextBundle::from_section(
-            "0",
-            TextStyle {
-                font: asset_server.load("fonts/HoneyBee-Regular.ttf"),
-                font_size: 120.0,
-                color: Color::rgb(0.9, 0.8, 0.5),
-            },
-        )
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            position: UiRect {
-                top: Val::Percent(9.0),
-                left: Val::Percent(07.8),
-                ..Default::default()
-            },
-            ..Default::default()
-        }),
+        score_text_bundle("0".to_string(), asset_server, settings),
         ScoreText,
         BeeGameMarker,
     ));
@@ -134,33 +296,80 @@ fn setup_scoreboard(commands: &mut Commands, asset_server: &Res<AssetServer>) {
     ));
 }
 
+/// The score drawn over the scoreboard sprite, in world space so it stays
+/// put whatever the window size.
+pub fn score_text_bundle(
+    score: String,
+    asset_server: &Res<AssetServer>,
+    settings: &Settings,
+) -> Text2dBundle {
+    Text2dBundle {
+        text: Text::from_section(
+            score,
+            TextStyle {
+                font: asset_server.load("fonts/HoneyBee-Regular.ttf"),
+                font_size: 120.0,
+                color: settings.palette.score_text(),
+            },
+        ),
+        text_anchor: Anchor::TopLeft,
+        transform: Transform::from_xyz(-540.0, 295.0, 130.0),
+        ..Default::default()
+    }
+}
+
 #[derive(Component)]
 struct Pillar {
     passed_bee: bool,
     y_offset: f32,
+    /// Whose world the pillar is in.
+    player: usize,
+    kind: ObstacleKind,
 }
 
-struct AABB {
-    l: f32,
-    r: f32,
-    t: f32,
-    b: f32,
+pub struct Aabb {
+    pub l: f32,
+    pub r: f32,
+    pub t: f32,
+    pub b: f32,
 }
 
-impl AABB {
-    fn is_touching(&self, self_t: &Transform, other: &AABB, other_t: &Transform) -> bool {
-        let self_tl = self_t.transform_point(Vec3::new(self.l, self.t, 0.0));
-        let self_br = self_t.transform_point(Vec3::new(self.r, self.b, 0.0));
-        let other_tl = other_t.transform_point(Vec3::new(other.l, other.t, 0.0));
-        let other_br = other_t.transform_point(Vec3::new(other.r, other.b, 0.0));
+impl Aabb {
+    /// Tests the boxes as they're drawn, tilted or squashed, by looking for
+    /// a gap along any of their edges.
+    fn is_touching(&self, self_t: &Transform, other: &Aabb, other_t: &Transform) -> bool {
+        let a = self.world_corners(self_t);
+        let b = other.world_corners(other_t);
+
+        let separated = |axis: Vec2| {
+            let project = |corners: &[Vec2; 4]| {
+                corners.iter().fold((f32::MAX, f32::MIN), |(min, max), c| {
+                    let d = c.dot(axis);
+                    (min.min(d), max.max(d))
+                })
+            };
+            let (a_min, a_max) = project(&a);
+            let (b_min, b_max) = project(&b);
+            a_max <= b_min || b_max <= a_min
+        };
+
+        // a box's other two edges are parallel to these
+        ![a[1] - a[0], a[3] - a[0], b[1] - b[0], b[3] - b[0]]
+            .into_iter()
+            .any(|edge| separated(edge.perp()))
+    }
 
-        self_tl.x < other_br.x
-            && self_br.x > other_tl.x
-            && self_br.y < other_tl.y
-            && self_tl.y > other_br.y
+    fn world_corners(&self, t: &Transform) -> [Vec2; 4] {
+        [
+            Vec3::new(self.l, self.t, 0.0),
+            Vec3::new(self.r, self.t, 0.0),
+            Vec3::new(self.r, self.b, 0.0),
+            Vec3::new(self.l, self.b, 0.0),
+        ]
+        .map(|c| t.transform_point(c).truncate())
     }
 
-    fn _display(&self, debug_lines: &mut ResMut<DebugLines>, t: &Transform) {
+    fn display(&self, debug_lines: &mut ResMut<DebugLines>, t: &Transform) {
         let tl = t.transform_point(Vec3::new(self.l, self.t, 0.0));
         let br = t.transform_point(Vec3::new(self.r, self.b, 0.0));
         let tr = t.transform_point(Vec3::new(self.r, self.t, 0.0));
@@ -178,17 +387,23 @@ impl AABB {
 }
 
 #[derive(Component)]
-struct Collider {
-    colliders: Vec<AABB>,
+pub struct Collider {
+    pub colliders: Vec<Aabb>,
+}
+
+/// Anything besides pillars that a bee shouldn't touch, in one player's
+/// world.
+#[derive(Component)]
+pub struct Harmful {
+    pub player: usize,
 }
 
 impl Collider {
     fn is_touching(&self, self_t: &Transform, other: &Collider, other_t: &Transform) -> bool {
         for a in &self.colliders {
             for b in &other.colliders {
-                match a.is_touching(self_t, b, other_t) {
-                    true => return true,
-                    false => (),
+                if a.is_touching(self_t, b, other_t) {
+                    return true;
                 }
             }
         }
@@ -196,20 +411,21 @@ impl Collider {
         false
     }
 
-    fn _display(&self, debug_lines: &mut ResMut<DebugLines>, t: &Transform) {
+    fn display(&self, debug_lines: &mut ResMut<DebugLines>, t: &Transform) {
         for aabb in &self.colliders {
-            aabb._display(debug_lines, t);
+            aabb.display(debug_lines, t);
         }
     }
 }
 
-fn setup_pillars(
+pub fn setup_pillars(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     wins: &Query<&Window>,
     texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    mode: GameMode,
 ) {
-    let window = wins.single();
+    let size = view::visible_size(wins.single());
 
     let texture_atlas = texture_atlases.add(TextureAtlas::from_grid(
         asset_server.load("textures/propeller.png"),
@@ -220,22 +436,33 @@ fn setup_pillars(
         None,
     ));
 
-    let mut timer = Timer::new(Duration::from_millis(2500), TimerMode::Repeating);
-    timer.set_elapsed(Duration::from_millis(2500));
+    let every = Duration::from_secs_f32(sim::SPAWN_EVERY);
+    let mut timer = Timer::new(every, TimerMode::Repeating);
+    timer.set_elapsed(every);
+    let look = Biome::default().pillars();
     let pillar_shared = PillarShared {
-        x_vel: 150.0,
-        y_pos: 0.0,
-        y_vel: 0.0,
-        x_pos_bounds: (-window.width() / 2.0 - 100.0, window.width() / 2.0 + 100.0),
-        y_pos_bounds: (-200.0, 200.0),
+        x_vel: sim::BASE_SPEED * mode.rules().speed,
+        players: mode.players(),
+        x_pos_bounds: pillar_bounds(size),
+        gap_scale: mode.rules().gap_scale,
         spawn_timer: timer,
-        texture: asset_server.load("textures/pipe.png"),
+        spawned: 0,
+        hold: None,
+        texture: asset_server.load(look.texture),
+        look,
         prop_texture: texture_atlas,
     };
 
     commands.insert_resource(pillar_shared);
 }
 
+fn pillar_bounds(size: Vec2) -> (f32, f32) {
+    // kept where the leaderboard believes pillars can come in, even if
+    // that's on screen for an unusually wide window
+    let left = (-size.x / 2.0 - 100.0).clamp(SPAWN_X.0, SPAWN_X.1);
+    (left, size.x / 2.0 + 100.0)
+}
+
 #[derive(Component)]
 pub struct BeeFly {
     pub aim: Vec2,
@@ -245,120 +472,107 @@ pub struct BeeFly {
     pub timer: Timer,
 }
 
+/// The bee in classic controls, flown by the player with `Player::y_vel`
+/// instead of wandering about.
 #[derive(Component)]
-pub struct AnimInfo {
-    pub timer: Timer,
-    pub num: usize,
+struct Piloted;
+
+/// Added when the bee dies, dropping it out of the sky.
+#[derive(Component)]
+struct Falling {
+    vel: f32,
 }
 
-fn setup_bee(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-) {
-    let center = Vec2::new(350.0, 0.0);
-    let texture_atlas = texture_atlases.add(TextureAtlas::from_grid(
-        asset_server.load("textures/bee.png"),
-        Vec2::new(32.0, 32.0),
-        2,
-        1,
-        Some(Vec2::new(1.0, 0.0)),
-        None,
-    ));
+fn setup_bee(commands: &mut Commands, settings: &Settings, index: usize, players: usize) {
+    let center = Vec2::new(BEE_X, 0.0);
 
-    commands.spawn((
-        SpriteSheetBundle {
-            sprite: TextureAtlasSprite {
-                custom_size: Some(Vec2 { x: 80.0, y: 80.0 }),
+    let bee = commands
+        .spawn((
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    custom_size: Some(Vec2 { x: 80.0, y: 80.0 }),
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(center.x, center.y, 100.0),
                 ..Default::default()
             },
-            transform: Transform::from_xyz(center.x, center.y, 100.0),
-            texture_atlas,
-            ..Default::default()
-        },
-        BeeFly {
-            aim: center,
-            center,
-            width: 100.0,
-            height: 220.0,
-            timer: Timer::new(Duration::from_millis(4000), TimerMode::Repeating),
-        },
-        AnimInfo {
-            timer: Timer::new(Duration::from_millis(500), TimerMode::Repeating),
-            num: 2,
-        },
-        Collider {
-            colliders: vec![AABB {
-                l: -35.0,
-                r: 35.0,
-                t: 35.0,
-                b: -35.0,
-            }],
-        },
-        BeeGameMarker,
-    ));
-}
-
-fn setup_babees(commands: &mut Commands, asset_server: &Res<AssetServer>, wins: &Query<&Window>) {
-    let window = wins.single();
-    let respawn_bounds = (-window.width() / 2.0 - 200.0, window.width() / 2.0 + 200.0);
-    for _ in 0..20 {
-        let x = rand::random::<f32>() * (respawn_bounds.1 - respawn_bounds.0) + (respawn_bounds.0);
-        let y = window.height() * (rand::random::<f32>() - 0.5);
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_xyz(x, y, 60.0).with_scale(Vec3::splat(2.0)),
-                texture: asset_server.load("textures/babee.png"),
-                ..Default::default()
+            Dressed,
+            BeeFly {
+                aim: center,
+                center,
+                width: WANDER.0,
+                height: WANDER.1,
+                timer: Timer::new(Duration::from_millis(4000), TimerMode::Repeating),
+            },
+            Animator::new(Clip::Idle),
+            Collider {
+                colliders: vec![Aabb {
+                    l: -BEE_HALF_SIZE,
+                    r: BEE_HALF_SIZE,
+                    t: BEE_HALF_SIZE,
+                    b: -BEE_HALF_SIZE,
+                }],
             },
-            Cloud {
-                vel: rand::random::<f32>() * 2.0 + 1.0,
-                respawn_bounds,
+            ParticleEmitter::new(ParticleKind::Pollen, 25.0, Vec2::new(20.0, -15.0)),
+            Player {
+                index,
+                y_pos: 0.0,
+                y_vel: 0.0,
+                flap: false,
+                is_dead: false,
             },
             BeeGameMarker,
-        ));
+        ))
+        .id();
+
+    if settings.controls == Controls::Classic {
+        commands.entity(bee).insert(Piloted);
+    }
+    if players > 1 {
+        commands
+            .entity(bee)
+            .insert(RenderLayers::layer(player_layer(index)));
     }
 }
 
-#[derive(Component)]
-struct Cloud {
-    vel: f32,
-    respawn_bounds: (f32, f32),
-}
-
-fn setup_clouds(commands: &mut Commands, asset_server: &Res<AssetServer>, wins: &Query<&Window>) {
-    let window = wins.single();
-    for _ in 0..10 {
-        let respawn_bounds = (-window.width() / 2.0 - 200.0, window.width() / 2.0 + 200.0);
-        let x = rand::random::<f32>() * (respawn_bounds.1 - respawn_bounds.0) + (respawn_bounds.0);
-        let y = window.height() * (rand::random::<f32>() - 0.5);
-        commands.spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2 {
-                        x: 120.0,
-                        y: 120.0 * 0.695,
-                    }),
-                    ..Default::default()
-                },
-                transform: Transform::from_xyz(x, y, 50.0),
-                texture: asset_server.load("textures/cloud1.png"),
-                ..Default::default()
-            },
-            Cloud {
-                vel: rand::random::<f32>() * 1.0 + 0.5,
-                respawn_bounds,
-            },
-            BeeGameMarker,
-        ));
+fn resize_bounds(
+    mut events: EventReader<WindowResized>,
+    wins: Query<&Window>,
+    mut pillar_shared: ResMut<PillarShared>,
+) {
+    if events.iter().last().is_none() {
+        return;
     }
+
+    pillar_shared.x_pos_bounds = pillar_bounds(view::visible_size(wins.single()));
 }
 
-fn clouds_move(mut query: Query<(&mut Transform, &Cloud)>) {
-    for (mut t, c) in query.iter_mut() {
-        t.translation.x -= c.vel;
-        if t.translation.x < c.respawn_bounds.0 {
-            t.translation.x = c.respawn_bounds.1;
+/// Keeps each versus camera on its half of the window.
+fn split_viewports(
+    wins: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &SplitView)>,
+) {
+    let Ok(window) = wins.get_single() else {
+        return;
+    };
+    let size = UVec2::new(window.physical_width() / 2, window.physical_height());
+    // a minimised window has nothing to draw to
+    if size.x == 0 || size.y == 0 {
+        return;
+    }
+
+    for (mut camera, split) in cameras.iter_mut() {
+        let position = UVec2::new(size.x * split.0 as u32, 0);
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|v| (v.physical_position, v.physical_size));
+        if current != Some((position, size)) {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: size,
+                ..default()
+            });
         }
     }
 }
@@ -380,126 +594,281 @@ fn pillar_move(
 fn pillar_spawner(
     mut commands: Commands,
     mut pillar_shared: ResMut<PillarShared>,
+    mut rng: ResMut<PillarRng>,
+    mut bosses: EventWriter<BossArrives>,
+    mode: Res<GameMode>,
     time: Res<Time>,
 ) {
+    // levels place their own pillars
+    if *mode == GameMode::Level {
+        return;
+    }
+
+    // keeping in step with `sim::spawn_time`
+    if let Some(hold) = pillar_shared.hold.as_mut() {
+        if !hold.tick(time.delta()).finished() {
+            return;
+        }
+        pillar_shared.hold = None;
+    }
+
     pillar_shared.spawn_timer.tick(time.delta());
 
     if pillar_shared.spawn_timer.just_finished() {
-        spawn_piller(&mut commands, &pillar_shared);
+        let y_offset = rng.0.next_offset();
+        let gap_scale = pillar_shared.gap_scale;
+        for player in 0..pillar_shared.players {
+            spawn_piller(
+                &mut commands,
+                &pillar_shared,
+                player,
+                y_offset,
+                gap_scale,
+                ObstacleKind::Pipe,
+            );
+        }
+
+        pillar_shared.spawned += 1;
+        if pillar_shared.spawned.is_multiple_of(sim::BOSS_EVERY) {
+            pillar_shared.hold = Some(Timer::from_seconds(sim::BOSS_TIME, TimerMode::Once));
+            bosses.send(BossArrives(pillar_shared.spawned / sim::BOSS_EVERY - 1));
+        }
     }
 }
 
+/// Clearance, in pixels, under which passing a pillar counts as a near miss.
+const NEAR_MISS: f32 = 15.0;
+
+#[allow(clippy::too_many_arguments)]
 fn pillar_score(
     mut pillars: Query<(&Transform, &mut Pillar)>,
-    bees: Query<(&Transform, &BeeFly), Without<Pillar>>,
+    mut bees: Query<(&Transform, &BeeFly, &mut Animator, &Player), Without<Pillar>>,
     mut game_info: ResMut<GameInfo>,
+    mut high_scores: ResMut<HighScores>,
+    mut new_high_score: EventWriter<NewHighScore>,
+    mut camera: EventWriter<CameraKick>,
+    mut tally: EventWriter<Tally>,
+    mode: Res<GameMode>,
 ) {
-    let bee = bees.single();
+    if game_info.is_dead {
+        return;
+    }
 
     for (t, mut p) in pillars.iter_mut() {
-        if !p.passed_bee {
-            if t.translation.x > bee.1.center.x {
-                p.passed_bee = true;
+        let Some(mut bee) = bees.iter_mut().find(|bee| bee.3.index == p.player) else {
+            continue;
+        };
+        if !p.passed_bee && t.translation.x > bee.1.center.x {
+            p.passed_bee = true;
+            // every player has a copy of each pillar, only count the one
+            if p.player == 0 {
                 game_info.score += 1;
+                camera.send(CameraKick::ZoomPulse);
+                tally.send(Tally::Pillar);
+            }
+
+            let bee_y = bee.0.translation.y;
+            let top = t.translation.y + GAP_TOP * t.scale.y - (bee_y + BEE_HALF_SIZE);
+            let bottom = (bee_y - BEE_HALF_SIZE) - (t.translation.y + GAP_BOTTOM * t.scale.y);
+            if top.min(bottom) < NEAR_MISS {
+                camera.send(CameraKick::Shake(0.35));
+                bee.2.play(Clip::Hurt);
+                if p.player == 0 {
+                    tally.send(Tally::NearMiss);
+                }
             }
         }
     }
-}
 
-fn spawn_piller(commands: &mut Commands, pillar_shared: &ResMut<PillarShared>) {
-    const HALF_WID: f32 = 24.0;
-
-    let y_offset = (rand::random::<f32>() - 0.5) * 200.0;
+    let best = high_scores.best(*mode);
+    if game_info.score > best && *mode != GameMode::Level {
+        // don't celebrate beating an empty table
+        if !game_info.beat_best && best > 0 {
+            new_high_score.send(NewHighScore);
+        }
+        game_info.beat_best = true;
+        high_scores.set_best(*mode, game_info.score);
+    }
+}
 
-    commands
-        .spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    custom_size: None,
-                    ..Default::default()
-                },
-                transform: Transform::from_xyz(pillar_shared.x_pos_bounds.0, 0.0, 80.0)
-                    .with_scale(Vec3::ONE * 2.5),
-                texture: pillar_shared.texture.clone(),
+/// Spawns a pillar at the edge of `player`'s screen with its gap `y_offset`
+/// off centre and squeezed by `gap_scale`.
+pub fn spawn_piller(
+    commands: &mut Commands,
+    pillar_shared: &PillarShared,
+    player: usize,
+    y_offset: f32,
+    gap_scale: f32,
+    kind: ObstacleKind,
+) -> Entity {
+    let layer = pillar_shared.layer(player);
+    let look = pillar_shared.look;
+    let (size, anchor) = look.fit();
+    let mut pillar = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(size),
+                anchor,
+                color: look.tint,
                 ..Default::default()
             },
-            Pillar {
-                passed_bee: false,
-                y_offset,
-            },
-            Collider {
-                colliders: vec![
-                    AABB {
-                        l: -HALF_WID,
-                        r: HALF_WID,
-                        t: 1000.0,
-                        b: 43.0,
-                    },
-                    AABB {
-                        l: -HALF_WID,
-                        r: HALF_WID,
-                        t: -50.0,
-                        b: -1000.0,
-                    },
-                ],
-            },
-            BeeGameMarker,
-        ))
-        .with_children(|parent| {
-            parent.spawn((
-                SpriteSheetBundle {
-                    transform: Transform::from_xyz(0.0, 80.0, 0.0),
-                    texture_atlas: pillar_shared.prop_texture.clone(),
-                    ..Default::default()
-                },
-                AnimInfo {
-                    timer: Timer::new(Duration::from_millis(250), TimerMode::Repeating),
-                    num: 2,
-                },
-            ));
-            parent.spawn((
-                SpriteSheetBundle {
-                    transform: Transform::from_xyz(0.0, -90.0, 0.0),
-                    texture_atlas: pillar_shared.prop_texture.clone(),
-                    ..Default::default()
+            transform: Transform::from_xyz(pillar_shared.x_pos_bounds.0, 0.0, 80.0)
+                .with_scale(Vec3::new(PILLAR_SCALE, PILLAR_SCALE * gap_scale, 1.0)),
+            texture: pillar_shared.texture.clone(),
+            ..Default::default()
+        },
+        Pillar {
+            passed_bee: false,
+            y_offset,
+            player,
+            kind,
+        },
+        Collider {
+            colliders: vec![
+                Aabb {
+                    l: -PILLAR_HALF_WIDTH,
+                    r: PILLAR_HALF_WIDTH,
+                    t: 1000.0,
+                    b: GAP_TOP,
                 },
-                AnimInfo {
-                    timer: Timer::new(Duration::from_millis(250), TimerMode::Repeating),
-                    num: 2,
+                Aabb {
+                    l: -PILLAR_HALF_WIDTH,
+                    r: PILLAR_HALF_WIDTH,
+                    t: GAP_BOTTOM,
+                    b: -1000.0,
                 },
-            ));
-        });
+            ],
+        },
+        BeeGameMarker,
+    ));
+    if look.propellers {
+        pillar
+            .insert(SfxEmitter::new(SoundEffect::Propeller))
+            .with_children(|parent| {
+                for y in [80.0, -90.0] {
+                    let mut propeller = parent.spawn((
+                        SpriteSheetBundle {
+                            transform: Transform::from_xyz(0.0, y, 0.0),
+                            texture_atlas: pillar_shared.prop_texture.clone(),
+                            ..Default::default()
+                        },
+                        Animator::new(Clip::Spin),
+                    ));
+                    if let Some(layer) = layer {
+                        propeller.insert(layer);
+                    }
+                }
+            });
+    }
+    if let Some(layer) = layer {
+        pillar.insert(layer);
+    }
+    pillar.id()
 }
 
 fn jump_input(
     keys: Res<Input<KeyCode>>,
-    mut pillar_shared: ResMut<PillarShared>,
-    time: Res<Time>,
-    asset_server: Res<AssetServer>,
-    audio: Res<Audio>,
+    buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    mut bees: Query<(&Transform, &mut Animator, &mut Player)>,
+    mut sfx: EventWriter<PlaySfx>,
+    mut tally: EventWriter<Tally>,
 ) {
-    if keys.just_pressed(KeyCode::Space) {
-        pillar_shared.y_vel = 4.0;
-        audio.play(asset_server.load("sounds/beep.wav"));
+    let pad = gamepads
+        .iter()
+        .any(|pad| buttons.just_pressed(GamepadButton::new(pad, GamepadButtonType::South)));
+
+    for (t, mut anim, mut player) in bees.iter_mut() {
+        let pressed = match player.index {
+            0 => keys.just_pressed(KeyCode::Space),
+            // enter too, for want of a pad
+            _ => pad || keys.just_pressed(KeyCode::Return),
+        };
+        if pressed && !player.is_dead {
+            player.flap = true;
+            sfx.send(PlaySfx::at(SoundEffect::Flap, t.translation.x));
+            anim.play(Clip::Flap);
+            if player.index == 0 {
+                tally.send(Tally::Flap);
+            }
+        }
     }
-
-    pillar_shared.y_vel -= 10.0 * time.delta_seconds();
 }
 
+#[allow(clippy::type_complexity)]
 fn let_it_jump(
-    mut pillar_shared: ResMut<PillarShared>,
+    pillar_shared: Res<PillarShared>,
+    mut clock: ResMut<FlightClock>,
+    mut flapped: EventWriter<Flapped>,
     mut query: Query<(&mut Transform, &Pillar)>,
+    mut players: Query<
+        (
+            &mut Transform,
+            &mut Player,
+            Option<&Piloted>,
+            Option<&Falling>,
+        ),
+        Without<Pillar>,
+    >,
+    time: Res<Time>,
+    settings: Res<Settings>,
 ) {
-    pillar_shared.y_pos += pillar_shared.y_vel;
-    pillar_shared.y_pos = pillar_shared
-        .y_pos
-        .clamp(pillar_shared.y_pos_bounds.0, pillar_shared.y_pos_bounds.1);
+    let classic = settings.controls == Controls::Classic;
+
+    // stepped at a fixed rate, which a hit-stop's paused time holds back
+    clock.owed += time.delta_seconds();
+    while clock.owed >= FLIGHT_STEP {
+        clock.owed -= FLIGHT_STEP;
+        let at = clock.steps as f32 * FLIGHT_STEP;
+        clock.steps += 1;
+        let pull = clock.weather.step();
+
+        for (mut t, mut player, piloted, falling) in players.iter_mut() {
+            // the bee carries the velocity in classic controls, and the
+            // world stays put
+            if classic && (piloted.is_none() || falling.is_some()) {
+                continue;
+            }
+            let mut flight = Flight {
+                y: match classic {
+                    true => t.translation.y,
+                    false => player.y_pos,
+                },
+                y_vel: player.y_vel,
+            };
+            flight.step(player.flap, pull, classic);
+            match classic {
+                true => t.translation.y = flight.y,
+                false => player.y_pos = flight.y,
+            }
+            player.y_vel = flight.y_vel;
+
+            if player.flap {
+                player.flap = false;
+                flapped.send(Flapped {
+                    player: player.index,
+                    at,
+                });
+            }
+        }
+    }
+
+    let mut worlds = vec![0.0; pillar_shared.players];
+    for (_, player, ..) in players.iter() {
+        if let Some(world) = worlds.get_mut(player.index) {
+            *world = player.y_pos;
+        }
+    }
     for (mut t, p) in query.iter_mut() {
-        t.translation.y = pillar_shared.y_pos + p.y_offset;
+        t.translation.y = worlds.get(p.player).copied().unwrap_or(0.0) + p.y_offset;
     }
 }
 
-fn bee_fly(mut query: Query<(&mut Transform, &mut BeeFly)>, time: Res<Time>) {
+#[allow(clippy::type_complexity)]
+fn bee_fly(
+    mut query: Query<(&mut Transform, &mut BeeFly), (Without<Falling>, Without<Piloted>)>,
+    time: Res<Time>,
+) {
     for (mut t, mut b) in query.iter_mut() {
         b.timer.tick(time.delta());
         if b.timer.just_finished() {
@@ -518,60 +887,228 @@ fn bee_fly(mut query: Query<(&mut Transform, &mut BeeFly)>, time: Res<Time>) {
     }
 }
 
-#[derive(Component)]
-struct ColorText;
-
 fn score_text_update(game_info: Res<GameInfo>, mut query: Query<&mut Text, With<ScoreText>>) {
     for mut text in query.iter_mut() {
         text.sections[0].value = format!("{}", game_info.score);
     }
 }
 
-fn anim_handler(mut query: Query<(&mut TextureAtlasSprite, &mut AnimInfo)>, time: Res<Time>) {
-    for (mut tas, mut ai) in query.iter_mut() {
-        ai.timer.tick(time.delta());
-
-        if ai.timer.just_finished() {
-            tas.index = (tas.index + 1) % ai.num;
+fn bee_cues(
+    mut commands: Commands,
+    mut cues: EventReader<AnimationCue>,
+    bees: Query<&Transform, With<BeeFly>>,
+    mut particles: EventWriter<SpawnParticles>,
+) {
+    for event in cues.iter() {
+        let Ok(t) = bees.get(event.entity) else {
+            continue;
+        };
+        match event.cue {
+            Cue::WingBurst => particles.send(SpawnParticles {
+                kind: ParticleKind::Puff,
+                at: t.translation.truncate(),
+            }),
+            Cue::Fall => {
+                commands.entity(event.entity).insert(Falling { vel: 300.0 });
+            }
+            Cue::Fallen => (),
         }
     }
 }
 
-fn audio_setup(asset_server: Res<AssetServer>, audio: Res<Audio>) {
-    audio.play(asset_server.load("sounds/farm2.mp3"));
+/// Radians of tilt per unit of `Player::y_vel`.
+const TILT: f32 = 0.12;
+const MAX_TILT: f32 = 0.6;
+/// Stretch per unit of `y_vel`, squashing flat as the bee dives.
+const STRETCH: f32 = 0.05;
+
+fn bee_tilt(mut query: Query<(&mut Transform, &Player), Without<Falling>>, time: Res<Time>) {
+    let ease = (12.0 * time.delta_seconds()).min(1.0);
+
+    for (mut t, player) in query.iter_mut() {
+        let y_vel = player.y_vel;
+        // the bee faces left, so nose up is clockwise
+        let angle = (-y_vel * TILT).clamp(-MAX_TILT, MAX_TILT);
+        let stretch = (1.0 + y_vel * STRETCH).clamp(0.85, 1.2);
+        t.rotation = t.rotation.slerp(Quat::from_rotation_z(angle), ease);
+        // keep the area the same so it reads as squash rather than growth
+        let scale = Vec3::new(1.0 / stretch, stretch, 1.0);
+        t.scale = t.scale.lerp(scale, ease);
+    }
+}
+
+fn bee_fall(mut query: Query<(&mut Transform, &mut Falling)>, time: Res<Time>) {
+    for (mut t, mut f) in query.iter_mut() {
+        f.vel -= 1500.0 * time.delta_seconds();
+        t.translation.y += f.vel * time.delta_seconds();
+        t.rotate_z(-6.0 * time.delta_seconds());
+    }
 }
 
+/// Speed a pillar knocks the bee back at when it can't die.
+const BOUNCE: f32 = 4.0;
+
+#[allow(clippy::too_many_arguments)]
 fn collisions(
-    bees: Query<(&Transform, &Collider), Without<Pillar>>,
-    pillars: Query<(&Transform, &Collider), With<Pillar>>,
+    mut bees: Query<(&Transform, &Collider, &mut Animator, &mut Player), Without<Pillar>>,
+    pillars: Query<(&Transform, &Collider, &Pillar)>,
+    harmful: Query<(&Transform, &Collider, &Harmful)>,
     mut game_info: ResMut<GameInfo>,
-    asset_server: Res<AssetServer>,
-    audio: Res<Audio>,
+    mut sfx: EventWriter<PlaySfx>,
+    mut duck: EventWriter<DuckMusic>,
+    mut particles: EventWriter<SpawnParticles>,
+    mut camera: EventWriter<CameraKick>,
+    mut tally: EventWriter<Tally>,
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
 ) {
-    let bee = bees.single();
-    let mut collided = false;
+    if game_info.is_dead {
+        return;
+    }
+
+    for (bee_t, bee_c, mut anim, mut player) in bees.iter_mut() {
+        if player.is_dead {
+            continue;
+        }
+
+        let bee_y = bee_t.translation.y;
+        // which way the bee needs to go to get clear, +1 being up
+        let mut hit = (bee_y < FLOOR).then_some((1.0, Hazard::Floor));
+
+        for (t, c, p) in pillars.iter() {
+            if p.player == player.index && c.is_touching(t, bee_c, bee_t) {
+                let gap_centre = t.translation.y + (GAP_TOP + GAP_BOTTOM) / 2.0 * t.scale.y;
+                hit = Some(((gap_centre - bee_y).signum(), Hazard::Pillar(p.kind)));
+            }
+        }
+        for (t, c, h) in harmful.iter() {
+            if h.player == player.index && c.is_touching(t, bee_c, bee_t) {
+                hit = Some(((bee_y - t.translation.y).signum(), Hazard::Boss));
+            }
+        }
 
-    for (t, c) in pillars.iter() {
-        if c.is_touching(t, bee.1, bee.0) {
-            collided = true;
+        let Some((away, hazard)) = hit else {
+            continue;
+        };
+
+        if !mode.can_die() {
+            // the world moves the opposite way to the bee in the original controls
+            let sign = match settings.controls {
+                Controls::Classic => 1.0,
+                Controls::World => -1.0,
+            };
+            if player.y_vel * sign * away <= 0.0 {
+                player.y_vel = BOUNCE * sign * away;
+                game_info.bounces += 1;
+                anim.play(Clip::Hurt);
+                sfx.send(PlaySfx::at(SoundEffect::Flap, bee_t.translation.x));
+                camera.send(CameraKick::Shake(0.2));
+            }
+        } else {
+            player.is_dead = true;
+            anim.play(Clip::Dead);
+            sfx.send(PlaySfx::at(SoundEffect::Death, bee_t.translation.x));
+            duck.send(DuckMusic);
+            particles.send(SpawnParticles {
+                kind: ParticleKind::Spark,
+                at: bee_t.translation.truncate(),
+            });
+            camera.send(CameraKick::Shake(0.8));
+            camera.send(CameraKick::HitStop(0.3));
+            if player.index == 0 {
+                tally.send(Tally::Crash(hazard));
+            }
         }
     }
 
-    if collided {
+    // in versus the first crash settles it, leaving the winner flying
+    let alive = bees.iter().filter(|bee| !bee.3.is_dead).count();
+    if alive < mode.players() {
         game_info.is_dead = true;
-        audio.play(asset_server.load("sounds/dead.wav"));
     }
 }
 
-fn _display_colliders(mut debug_lines: ResMut<DebugLines>, query: Query<(&Transform, &Collider)>) {
+fn display_colliders(mut debug_lines: ResMut<DebugLines>, query: Query<(&Transform, &Collider)>) {
     for (t, c) in query.iter() {
-        c._display(&mut debug_lines, t);
+        c.display(&mut debug_lines, t);
     }
 }
 
-fn game_killer(game_info: Res<GameInfo>, mut game_state: ResMut<NextState<GameState>>) {
-    if game_info.is_dead {
-        game_state.set(GameState::Menu);
+/// Seconds the winner of a versus run flies on alone.
+const VICTORY_LAP: f32 = 2.0;
+
+#[allow(clippy::too_many_arguments)]
+fn game_killer(
+    mut commands: Commands,
+    mut cues: EventReader<AnimationCue>,
+    mut game_info: ResMut<GameInfo>,
+    level_run: Option<ResMut<LevelRun>>,
+    level_assets: Res<Assets<Level>>,
+    pillars: Query<Entity, With<Pillar>>,
+    mut bees: Query<(
+        Entity,
+        &mut Transform,
+        &mut Animator,
+        &mut BeeFly,
+        &mut Player,
+    )>,
+    mode: Res<GameMode>,
+    time: Res<Time>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    // the run is over once the bee has finished falling
+    if cues.iter().any(|event| event.cue == Cue::Fallen) {
+        // unless a level can pick up again from a checkpoint
+        let resume = level_run.and_then(|mut run| {
+            let level = level_assets.get(&run.level)?;
+            run.rewind(level)
+        });
+        let Some(score) = resume else {
+            if mode.players() > 1 {
+                // give the winner a moment to enjoy it
+                game_info.clock = Some(Timer::from_seconds(VICTORY_LAP, TimerMode::Once));
+            } else {
+                game_state.set(after_run(*mode));
+            }
+            return;
+        };
+
+        for e in pillars.iter() {
+            commands.entity(e).despawn_recursive();
+        }
+        game_info.score = score;
+        game_info.is_dead = false;
+        for (e, mut t, mut anim, mut fly, mut player) in bees.iter_mut() {
+            player.y_pos = 0.0;
+            player.y_vel = 0.0;
+            player.flap = false;
+            player.is_dead = false;
+            commands.entity(e).remove::<Falling>();
+            *t = Transform::from_translation(fly.center.extend(t.translation.z));
+            fly.aim = fly.center;
+            anim.play(Clip::Idle);
+        }
+    }
+
+    // or when a timed run runs out
+    if let Some(clock) = &mut game_info.clock {
+        if clock.tick(time.delta()).just_finished() {
+            game_state.set(after_run(*mode));
+        }
+    }
+}
+
+/// Online races go back to their lobby rather than the menu.
+fn after_run(mode: GameMode) -> GameState {
+    match mode {
+        GameMode::Online => GameState::Lobby,
+        _ => GameState::Menu,
+    }
+}
+
+fn save_high_score(game_info: Res<GameInfo>, high_scores: Res<HighScores>) {
+    if game_info.beat_best {
+        high_scores.save();
     }
 }
 
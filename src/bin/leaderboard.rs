@@ -0,0 +1,181 @@
+//! Stand-in leaderboard server, for working on the game offline.
+//!
+//!     cargo run --bin leaderboard [address]
+//!
+//! `POST /scores` takes a RON `Submission`, flies its replay through the
+//! pillars and weather its seed lays out and answers with a `Receipt`.
+//! Bosses aren't flown against, so a run that gets past the pillars either
+//! side of one is taken to have survived it. `GET /scores/<board>` answers
+//! with the board's top entries. Boards are kept in `leaderboard.ron` in the
+//! working directory.
+
+#[allow(dead_code)]
+#[path = "../sim.rs"]
+mod sim;
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use sim::{Entry, Receipt, Submission, Verdict};
+
+const SAVE_FILE: &str = "leaderboard.ron";
+/// Entries kept per board.
+const KEPT: usize = 100;
+/// Entries sent back for a board.
+const TOP: usize = 10;
+const MAX_BODY: usize = 8 << 20;
+const MAX_NAME: usize = 16;
+
+type Boards = BTreeMap<String, Vec<Entry>>;
+
+fn main() -> io::Result<()> {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7878".to_string());
+    let listener = TcpListener::bind(&addr)?;
+    let mut boards: Boards = fs::read_to_string(SAVE_FILE)
+        .ok()
+        .and_then(|s| ron::from_str(&s).ok())
+        .unwrap_or_default();
+    println!("leaderboard listening on {}", addr);
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if let Err(e) = serve(stream, &mut boards) {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(())
+}
+
+fn serve(mut stream: TcpStream, boards: &mut Boards) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return respond(&mut stream, "413 Payload Too Large", "");
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8_lossy(&body);
+
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("POST"), Some("/scores")) => match ron::from_str::<Submission>(&body) {
+            Ok(run) => {
+                let receipt = submit(run, boards);
+                println!("{:?}", receipt.verdict);
+                respond(&mut stream, "200 OK", &to_ron(&receipt))
+            }
+            Err(e) => respond(&mut stream, "400 Bad Request", &e.to_string()),
+        },
+        (Some("GET"), Some(path)) if path.starts_with("/scores/") => {
+            let top = top(boards, &path["/scores/".len()..]);
+            respond(&mut stream, "200 OK", &to_ron(&top))
+        }
+        _ => respond(&mut stream, "404 Not Found", ""),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/ron\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+fn to_ron<T: serde::Serialize>(value: &T) -> String {
+    ron::to_string(value).unwrap_or_default()
+}
+
+fn top(boards: &Boards, board: &str) -> Vec<Entry> {
+    boards
+        .get(board)
+        .map(|entries| entries.iter().take(TOP).cloned().collect())
+        .unwrap_or_default()
+}
+
+fn submit(run: Submission, boards: &mut Boards) -> Receipt {
+    let board = sim::board(&run.mode, run.seed);
+    let verdict = match sim::verify(&run) {
+        Err(reason) => Verdict::Rejected(reason),
+        Ok(score) if score < run.score => Verdict::Rejected(format!(
+            "claimed {} but the replay reached {}",
+            run.score, score
+        )),
+        Ok(_) => {
+            let entries = boards.entry(board.clone()).or_default();
+            let rank = entries
+                .iter()
+                .position(|e| e.score < run.score)
+                .unwrap_or(entries.len());
+            if rank < KEPT {
+                entries.insert(
+                    rank,
+                    Entry {
+                        name: clean(&run.name),
+                        score: run.score,
+                    },
+                );
+                entries.truncate(KEPT);
+                save(boards);
+                Verdict::Ranked(rank + 1)
+            } else {
+                Verdict::Unranked
+            }
+        }
+    };
+    Receipt {
+        verdict,
+        top: top(boards, &board),
+    }
+}
+
+/// Keeps names short and printable.
+fn clean(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .take(MAX_NAME)
+        .collect();
+    match name.trim() {
+        "" => "Bee".to_string(),
+        name => name.to_string(),
+    }
+}
+
+fn save(boards: &Boards) {
+    let result = ron::ser::to_string_pretty(boards, Default::default())
+        .map_err(|e| e.to_string())
+        .and_then(|s| fs::write(SAVE_FILE, s).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("couldn't save {}: {}", SAVE_FILE, e);
+    }
+}
@@ -0,0 +1,304 @@
+use bevy::{prelude::*, sprite::Anchor};
+use serde::Deserialize;
+
+use crate::{
+    audio::{MusicTrack, PlayMusic},
+    bee_game::{GameInfo, PillarShared},
+    environment,
+    settings::Settings,
+    GameState,
+};
+
+/// Points between one biome and the next.
+const BIOME_EVERY: u32 = 15;
+/// Seconds the scenery takes to change over.
+const TRANSITION: f32 = 2.0;
+
+pub struct BiomesPlugin;
+
+impl Plugin for BiomesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scenery>()
+            .add_system(setup.in_schedule(OnEnter(GameState::Game)))
+            .add_system(advance.in_set(OnUpdate(GameState::Game)))
+            .add_system(flash.in_set(OnUpdate(GameState::Game)))
+            .add_system(cleanup.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum Biome {
+    #[default]
+    Meadow,
+    Greenhouse,
+    City,
+    Hive,
+}
+
+/// How a biome's pillars are drawn. The colliders don't change, so each
+/// texture is fitted to put its gap where `pipe.png`'s is.
+pub struct PillarLook {
+    pub texture: &'static str,
+    /// Of the texture, in pixels.
+    size: Vec2,
+    /// First and last rows of the gap, counted down from the top of the
+    /// texture, the last one not included.
+    gap: (f32, f32),
+    pub tint: Color,
+    pub propellers: bool,
+}
+
+const PIPE: PillarLook = PillarLook {
+    texture: "textures/pipe.png",
+    size: Vec2::new(54.0, 650.0),
+    gap: (288.0, 373.0),
+    tint: Color::WHITE,
+    propellers: true,
+};
+
+const OLD_PIPE: PillarLook = PillarLook {
+    texture: "textures/old_pipe.png",
+    size: Vec2::new(48.0, 500.0),
+    gap: (190.0, 283.0),
+    tint: Color::WHITE,
+    propellers: false,
+};
+
+const OLDER_PIPE: PillarLook = PillarLook {
+    texture: "textures/older_pipe.png",
+    size: Vec2::new(48.0, 500.0),
+    gap: (190.0, 283.0),
+    tint: Color::rgb(0.85, 0.85, 0.9),
+    propellers: false,
+};
+
+const HONEY_PIPE: PillarLook = PillarLook {
+    texture: "textures/pipe-finished.png",
+    size: Vec2::new(57.0, 650.0),
+    gap: (288.0, 373.0),
+    tint: Color::rgb(1.3, 0.95, 0.4),
+    propellers: true,
+};
+
+impl PillarLook {
+    /// Top and bottom of the gap in the sprite's local space, unfitted.
+    fn gap_edges(&self) -> (f32, f32) {
+        (
+            self.size.y / 2.0 - self.gap.0,
+            self.size.y / 2.0 - self.gap.1,
+        )
+    }
+
+    /// Sprite size and anchor lining the gap up with the standard pipe's.
+    pub fn fit(&self) -> (Vec2, Anchor) {
+        let (top, bottom) = self.gap_edges();
+        let (pipe_top, pipe_bottom) = PIPE.gap_edges();
+        let stretch = (pipe_top - pipe_bottom) / (top - bottom);
+        let height = self.size.y * stretch;
+        let shift = (pipe_top + pipe_bottom) / 2.0 - (top + bottom) / 2.0 * stretch;
+        (
+            Vec2::new(self.size.x, height),
+            Anchor::Custom(Vec2::new(0.0, -shift / height)),
+        )
+    }
+}
+
+impl Biome {
+    pub const ALL: [Biome; 4] = [Biome::Meadow, Biome::Greenhouse, Biome::City, Biome::Hive];
+
+    /// The biome a run is in at `score`, going round them all in turn.
+    pub fn at(score: u32) -> Self {
+        Self::ALL[(score / BIOME_EVERY) as usize % Self::ALL.len()]
+    }
+
+    /// English name, to be passed through `Language::tr`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Meadow => "Meadow",
+            Biome::Greenhouse => "Greenhouse",
+            Biome::City => "City",
+            Biome::Hive => "Hive",
+        }
+    }
+
+    pub fn pillars(self) -> &'static PillarLook {
+        match self {
+            Biome::Meadow => &PIPE,
+            Biome::Greenhouse => &OLD_PIPE,
+            Biome::City => &OLDER_PIPE,
+            Biome::Hive => &HONEY_PIPE,
+        }
+    }
+
+    /// Replaces the palette's sky colour, if set.
+    fn sky(self) -> Option<Color> {
+        match self {
+            Biome::Meadow => None,
+            Biome::Greenhouse => Some(Color::rgb(0.6, 0.9, 0.75)),
+            Biome::City => Some(Color::rgb(0.55, 0.62, 0.75)),
+            Biome::Hive => Some(Color::rgb(0.55, 0.33, 0.08)),
+        }
+    }
+
+    /// Multiplied over the background layers.
+    fn tint(self) -> Color {
+        match self {
+            Biome::Meadow => Color::WHITE,
+            Biome::Greenhouse => Color::rgb(0.85, 1.0, 0.85),
+            Biome::City => Color::rgb(0.8, 0.8, 0.9),
+            Biome::Hive => Color::rgb(1.0, 0.8, 0.5),
+        }
+    }
+
+    pub fn music(self) -> MusicTrack {
+        match self {
+            Biome::Meadow => MusicTrack::Game,
+            Biome::Greenhouse => MusicTrack::Greenhouse,
+            Biome::City => MusicTrack::City,
+            Biome::Hive => MusicTrack::Hive,
+        }
+    }
+}
+
+/// The biome a run is in, and the one it's leaving behind.
+#[derive(Resource)]
+pub struct Scenery {
+    pub biome: Biome,
+    previous: Biome,
+    /// From 0 when the biome changes up to 1 once the previous one is gone.
+    blend: f32,
+}
+
+impl Default for Scenery {
+    fn default() -> Self {
+        Self {
+            biome: Biome::default(),
+            previous: Biome::default(),
+            blend: 1.0,
+        }
+    }
+}
+
+impl Scenery {
+    pub fn sky(&self, palette_sky: Color) -> Color {
+        environment::mix(
+            self.previous.sky().unwrap_or(palette_sky),
+            self.biome.sky().unwrap_or(palette_sky),
+            self.blend,
+        )
+    }
+
+    pub fn tint(&self) -> Color {
+        environment::mix(self.previous.tint(), self.biome.tint(), self.blend)
+    }
+
+    /// How much of a layer shown in `biomes`, or everywhere if empty, is
+    /// showing.
+    pub fn presence(&self, biomes: &[Biome]) -> f32 {
+        let shown = |biome| match biomes.is_empty() || biomes.contains(&biome) {
+            true => 1.0,
+            false => 0.0,
+        };
+        let from = shown(self.previous);
+        from + (shown(self.biome) - from) * self.blend
+    }
+}
+
+#[derive(Component)]
+struct BiomesMarker;
+
+/// The wash of colour and the biome's name shown on the way in.
+#[derive(Component)]
+struct Flash(Timer);
+
+#[derive(Component)]
+struct FlashText;
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(Scenery::default());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn advance(
+    mut commands: Commands,
+    mut scenery: ResMut<Scenery>,
+    mut pillar_shared: ResMut<PillarShared>,
+    mut music: EventWriter<PlayMusic>,
+    game_info: Res<GameInfo>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    scenery.blend = (scenery.blend + time.delta_seconds() / TRANSITION).min(1.0);
+
+    let biome = Biome::at(game_info.score);
+    if biome == scenery.biome {
+        return;
+    }
+    let previous = scenery.biome;
+    *scenery = Scenery {
+        biome,
+        previous,
+        blend: 0.0,
+    };
+
+    // pillars already on screen keep their look
+    pillar_shared.set_look(biome.pillars(), &asset_server);
+    music.send(PlayMusic(biome.music()));
+
+    let sky = biome.sky().unwrap_or(settings.palette.sky());
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: sky.with_a(0.0).into(),
+                ..default()
+            },
+            Flash(Timer::from_seconds(TRANSITION, TimerMode::Once)),
+            BiomesMarker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    settings.language.tr(biome.name()),
+                    TextStyle {
+                        font: asset_server.load("fonts/HoneyBee-Regular.ttf"),
+                        font_size: 90.0,
+                        color: settings.palette.score_text(),
+                    },
+                ),
+                FlashText,
+            ));
+        });
+}
+
+fn flash(
+    mut commands: Commands,
+    mut flashes: Query<(Entity, &mut Flash, &mut BackgroundColor)>,
+    mut texts: Query<&mut Text, With<FlashText>>,
+    time: Res<Time>,
+) {
+    for (e, mut flash, mut background) in flashes.iter_mut() {
+        if flash.0.tick(time.delta()).finished() {
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+        let left = 1.0 - flash.0.percent();
+        background.0.set_a(0.5 * left * left);
+        for mut text in texts.iter_mut() {
+            text.sections[0].style.color.set_a((2.0 * left).min(1.0));
+        }
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<BiomesMarker>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
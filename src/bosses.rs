@@ -0,0 +1,483 @@
+use bevy::{prelude::*, render::view::RenderLayers, sprite::Anchor};
+
+use crate::{
+    animation::{Animator, Clip},
+    bee_game::{Aabb, BeeFly, Collider, Harmful, PillarShared, Player},
+    camera::CameraKick,
+    sfx::{SfxEmitter, SoundEffect},
+    GameState,
+};
+
+/// How far in from where pillars come the boss hangs about.
+const HOME: f32 = 350.0;
+/// Seconds to fly in.
+const ENTER_TIME: f32 = 2.0;
+const LEAVE_SPEED: f32 = 400.0;
+const CHARGE_SPEED: f32 = 900.0;
+const STING_SPEED: f32 = 550.0;
+/// Seconds an attack is warned of before it comes.
+const TELEGRAPH: f32 = 1.0;
+/// Seconds a boss is gone for before the pillars come back.
+const MARGIN: f32 = 1.5;
+/// Furthest off centre an attack is aimed, relative to the world.
+const MAX_AIM: f32 = 250.0;
+
+/// Bounding boxes of a boss facing right, as fractions of its size: body,
+/// head, stinger and wings.
+const PARTS: [[f32; 4]; 4] = [
+    [-0.3, 0.25, 0.15, -0.2],
+    [0.2, 0.4, 0.12, -0.12],
+    [-0.45, -0.28, -0.05, -0.18],
+    [-0.15, 0.1, 0.35, 0.12],
+];
+
+pub struct BossesPlugin;
+
+impl Plugin for BossesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BossArrives>()
+            .add_system(arrive.in_set(OnUpdate(GameState::Game)))
+            .add_system(fight.in_set(OnUpdate(GameState::Game)))
+            .add_system(stings.in_set(OnUpdate(GameState::Game)))
+            .add_system(warnings.in_set(OnUpdate(GameState::Game)))
+            .add_system(place.after(fight).in_set(OnUpdate(GameState::Game)))
+            .add_system(cleanup.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+/// Sent by the pillar spawner as it holds off for a boss, with how many
+/// have come before it.
+pub struct BossArrives(pub usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BossKind {
+    Wasp,
+    Hornet,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Attack {
+    /// A sting fired straight along the warned line.
+    Sting,
+    /// Three stings fanned out about it.
+    Volley,
+    /// The boss itself flies along it, right across the screen.
+    Charge,
+}
+
+/// One step of a boss's script.
+#[derive(Clone, Copy, Debug)]
+enum Move {
+    /// Flies in from where the pillars come from.
+    Enter,
+    /// Keeps to its pattern for a number of seconds.
+    Hover(f32),
+    /// Warns of an attack along the bee's line, then makes it.
+    Strike(Attack),
+    Leave,
+}
+
+impl BossKind {
+    const ALL: [BossKind; 2] = [BossKind::Wasp, BossKind::Hornet];
+
+    fn size(self) -> f32 {
+        match self {
+            BossKind::Wasp => 220.0,
+            BossKind::Hornet => 300.0,
+        }
+    }
+
+    fn tint(self) -> Color {
+        match self {
+            BossKind::Wasp => Color::rgb(1.4, 0.5, 0.3),
+            BossKind::Hornet => Color::rgb(0.9, 0.45, 0.15),
+        }
+    }
+
+    /// Where it hovers about `t` seconds in, relative to its home.
+    fn pattern(self, t: f32) -> Vec2 {
+        match self {
+            BossKind::Wasp => Vec2::new(40.0 * (3.0 * t).sin(), 140.0 * (1.5 * t).sin()),
+            BossKind::Hornet => Vec2::new(25.0 * t.sin(), 200.0 * (0.7 * t).sin()),
+        }
+    }
+
+    /// Played once through, ending with `Leave`. It's skipped to the end
+    /// if the pillars are due back before it's over, which on a wide
+    /// window they can be.
+    fn script(self) -> &'static [Move] {
+        use Move::*;
+        match self {
+            BossKind::Wasp => &[
+                Enter,
+                Hover(2.0),
+                Strike(Attack::Sting),
+                Hover(1.0),
+                Strike(Attack::Volley),
+                Hover(1.0),
+                Strike(Attack::Charge),
+                Enter,
+                Strike(Attack::Sting),
+                Leave,
+            ],
+            BossKind::Hornet => &[
+                Enter,
+                Hover(2.0),
+                Strike(Attack::Charge),
+                Enter,
+                Hover(1.0),
+                Strike(Attack::Volley),
+                Hover(1.0),
+                Strike(Attack::Volley),
+                Leave,
+            ],
+        }
+    }
+}
+
+impl Attack {
+    /// Angles the attack comes at, off straight across.
+    fn angles(self) -> &'static [f32] {
+        match self {
+            Attack::Sting | Attack::Charge => &[0.0],
+            Attack::Volley => &[-0.2, 0.0, 0.2],
+        }
+    }
+}
+
+/// Works through its kind's script.
+#[derive(Component)]
+struct Boss {
+    kind: BossKind,
+    player: usize,
+    step: usize,
+    /// Seconds into the step.
+    clock: f32,
+    started: bool,
+    /// Seconds since arriving, driving the hover pattern.
+    age: f32,
+    /// Height the attack being warned of will come along.
+    aim: f32,
+}
+
+/// Keeps its place in a player's world, moving up and down with it as the
+/// pillars do.
+#[derive(Component)]
+struct InWorld {
+    player: usize,
+    at: Vec2,
+}
+
+#[derive(Component)]
+struct Sting {
+    vel: Vec2,
+}
+
+#[derive(Component)]
+struct Warning(Timer);
+
+#[derive(Component)]
+struct BossMarker;
+
+fn arrive(
+    mut commands: Commands,
+    mut events: EventReader<BossArrives>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    pillar_shared: Res<PillarShared>,
+    mut atlas: Local<Option<Handle<TextureAtlas>>>,
+    mut camera: EventWriter<CameraKick>,
+) {
+    for BossArrives(count) in events.iter() {
+        let kind = BossKind::ALL[count % BossKind::ALL.len()];
+        let atlas = atlas.get_or_insert_with(|| {
+            texture_atlases.add(TextureAtlas::from_grid(
+                asset_server.load("textures/bee.png"),
+                Vec2::splat(32.0),
+                2,
+                1,
+                Some(Vec2::new(1.0, 0.0)),
+                None,
+            ))
+        });
+        let size = kind.size();
+
+        for player in 0..pillar_shared.players() {
+            let mut boss = commands.spawn((
+                SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        custom_size: Some(Vec2::splat(size)),
+                        color: kind.tint(),
+                        // bees face left, and the boss faces them
+                        flip_x: true,
+                        ..Default::default()
+                    },
+                    texture_atlas: atlas.clone(),
+                    transform: Transform::from_xyz(pillar_shared.spawn_x() - size, 0.0, 85.0),
+                    ..Default::default()
+                },
+                Animator::new(Clip::Buzz),
+                Collider {
+                    colliders: PARTS
+                        .iter()
+                        .map(|&[l, r, t, b]| Aabb {
+                            l: l * size,
+                            r: r * size,
+                            t: t * size,
+                            b: b * size,
+                        })
+                        .collect(),
+                },
+                Harmful { player },
+                InWorld {
+                    player,
+                    at: Vec2::new(pillar_shared.spawn_x() - size, 0.0),
+                },
+                Boss {
+                    kind,
+                    player,
+                    step: 0,
+                    clock: 0.0,
+                    started: false,
+                    age: 0.0,
+                    aim: 0.0,
+                },
+                SfxEmitter::new(SoundEffect::Propeller),
+                BossMarker,
+            ));
+            if let Some(layer) = pillar_shared.layer(player) {
+                boss.insert(layer);
+            }
+        }
+        camera.send(CameraKick::Shake(0.4));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn fight(
+    mut commands: Commands,
+    mut bosses: Query<(Entity, &mut Boss, &mut InWorld, Option<&RenderLayers>)>,
+    bees: Query<(&Transform, &Player), With<BeeFly>>,
+    pillar_shared: Res<PillarShared>,
+    mut camera: EventWriter<CameraKick>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    let home = pillar_shared.spawn_x() + HOME;
+    // past either side of the screen
+    let (near, far) = (pillar_shared.spawn_x(), -pillar_shared.spawn_x());
+
+    for (e, mut boss, mut place, layer) in bosses.iter_mut() {
+        let Some(&step) = boss.kind.script().get(boss.step) else {
+            commands.entity(e).despawn_recursive();
+            continue;
+        };
+        let size = boss.kind.size();
+
+        // leave while there's still time to be gone before the pillars
+        // come back, a charge under way being seen through
+        let left = pillar_shared.hold_left();
+        let wrap_up = match step {
+            Move::Leave => false,
+            Move::Strike(Attack::Charge) if boss.clock >= TELEGRAPH => false,
+            Move::Strike(Attack::Charge) => {
+                let cross = (far + size - home) / CHARGE_SPEED;
+                left < TELEGRAPH - boss.clock + cross + MARGIN
+            }
+            _ => left < (place.at.x - near + size).max(0.0) / LEAVE_SPEED + MARGIN,
+        };
+        let step = match wrap_up {
+            // off the far side after a charge, it can just go
+            true if place.at.x > far => {
+                commands.entity(e).despawn_recursive();
+                continue;
+            }
+            true => {
+                boss.step = boss.kind.script().len() - 1;
+                boss.clock = 0.0;
+                boss.started = false;
+                Move::Leave
+            }
+            false => step,
+        };
+        let start = !boss.started;
+        boss.started = true;
+        boss.clock += dt;
+        boss.age += dt;
+        let hover = Vec2::new(home, 0.0) + boss.kind.pattern(boss.age);
+        let ease = (3.0 * dt).min(1.0);
+
+        let done = match step {
+            Move::Enter => {
+                if start {
+                    place.at.x = near - size;
+                }
+                place.at = place.at.lerp(hover, ease);
+                boss.clock >= ENTER_TIME
+            }
+            Move::Hover(secs) => {
+                place.at = place.at.lerp(hover, ease);
+                boss.clock >= secs
+            }
+            Move::Strike(attack) => {
+                if start {
+                    boss.aim = bees
+                        .iter()
+                        .find(|(_, player)| player.index == boss.player)
+                        .map_or(0.0, |(t, player)| t.translation.y - player.y_pos)
+                        .clamp(-MAX_AIM, MAX_AIM);
+                    let from = Vec2::new(home, boss.aim);
+                    let width = match attack {
+                        Attack::Charge => size * 0.5,
+                        Attack::Sting | Attack::Volley => 6.0,
+                    };
+                    for &angle in attack.angles() {
+                        spawn_warning(&mut commands, boss.player, from, angle, width, layer);
+                    }
+                }
+
+                if boss.clock < TELEGRAPH {
+                    // line up with the warning
+                    place.at = place.at.lerp(Vec2::new(home, boss.aim), 2.0 * ease);
+                    false
+                } else if attack == Attack::Charge {
+                    if boss.clock - dt < TELEGRAPH {
+                        camera.send(CameraKick::Shake(0.3));
+                    }
+                    place.at.y = boss.aim;
+                    place.at.x += CHARGE_SPEED * dt;
+                    place.at.x > far + size
+                } else {
+                    let from = Vec2::new(place.at.x + size * 0.4, boss.aim);
+                    for &angle in attack.angles() {
+                        spawn_sting(&mut commands, boss.player, from, angle, layer);
+                    }
+                    true
+                }
+            }
+            Move::Leave => {
+                place.at.x -= LEAVE_SPEED * dt;
+                place.at.x < near - size
+            }
+        };
+
+        if done {
+            boss.step += 1;
+            boss.clock = 0.0;
+            boss.started = false;
+        }
+    }
+}
+
+fn spawn_warning(
+    commands: &mut Commands,
+    player: usize,
+    from: Vec2,
+    angle: f32,
+    width: f32,
+    layer: Option<&RenderLayers>,
+) {
+    let mut warning = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(1.0, 0.2, 0.1, 0.0),
+                custom_size: Some(Vec2::new(2000.0, width)),
+                anchor: Anchor::CenterLeft,
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(from.x, from.y, 84.0)
+                .with_rotation(Quat::from_rotation_z(angle)),
+            ..Default::default()
+        },
+        InWorld { player, at: from },
+        Warning(Timer::from_seconds(TELEGRAPH, TimerMode::Once)),
+        BossMarker,
+    ));
+    if let Some(layer) = layer {
+        warning.insert(*layer);
+    }
+}
+
+fn spawn_sting(
+    commands: &mut Commands,
+    player: usize,
+    from: Vec2,
+    angle: f32,
+    layer: Option<&RenderLayers>,
+) {
+    let mut sting = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.25, 0.05, 0.05),
+                custom_size: Some(Vec2::new(36.0, 6.0)),
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(from.x, from.y, 86.0)
+                .with_rotation(Quat::from_rotation_z(angle)),
+            ..Default::default()
+        },
+        Collider {
+            colliders: vec![Aabb {
+                l: -18.0,
+                r: 18.0,
+                t: 3.0,
+                b: -3.0,
+            }],
+        },
+        Harmful { player },
+        InWorld { player, at: from },
+        Sting {
+            vel: Vec2::from_angle(angle) * STING_SPEED,
+        },
+        BossMarker,
+    ));
+    if let Some(layer) = layer {
+        sting.insert(*layer);
+    }
+}
+
+fn stings(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut InWorld, &Sting)>,
+    pillar_shared: Res<PillarShared>,
+    time: Res<Time>,
+) {
+    for (e, mut place, sting) in query.iter_mut() {
+        place.at += sting.vel * time.delta_seconds();
+        if place.at.x > -pillar_shared.spawn_x() {
+            commands.entity(e).despawn_recursive();
+        }
+    }
+}
+
+fn warnings(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Warning, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (e, mut warning, mut sprite) in query.iter_mut() {
+        if warning.0.tick(time.delta()).finished() {
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+        let blink = (warning.0.elapsed_secs() * 20.0).sin().abs();
+        sprite.color.set_a(0.6 * blink);
+    }
+}
+
+fn place(players: Query<&Player>, mut query: Query<(&mut Transform, &InWorld)>) {
+    for (mut t, place) in query.iter_mut() {
+        let world = players
+            .iter()
+            .find(|p| p.index == place.player)
+            .map_or(0.0, |p| p.y_pos);
+        t.translation.x = place.at.x;
+        t.translation.y = world + place.at.y;
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<BossMarker>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{settings::Settings, GameState};
+
+/// Furthest the camera strays from centre at full trauma, in pixels.
+const MAX_OFFSET: f32 = 24.0;
+/// Largest roll at full trauma, in radians.
+const MAX_ROLL: f32 = 0.05;
+/// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.5;
+/// How far a zoom pulse pulls in, as a fraction of the view.
+const ZOOM_AMOUNT: f32 = 0.03;
+const ZOOM_DECAY: f32 = 4.0;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CameraKick>()
+            .init_resource::<HitStop>()
+            .add_systems(
+                (kicks, hit_stop.after(kicks), camera_fx.after(kicks))
+                    .in_set(OnUpdate(GameState::Game))
+                    .in_set(CameraFxSet),
+            )
+            .add_system(cleanup.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+/// Gameplay systems that kick the camera run before this, so a hit-stop
+/// starts the same frame it is asked for.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CameraFxSet;
+
+pub enum CameraKick {
+    /// Adds trauma, clamped to 1. Shake grows with its square so small
+    /// knocks stay subtle.
+    Shake(f32),
+    /// Freezes game time for the given number of seconds.
+    HitStop(f32),
+    ZoomPulse,
+}
+
+/// Added to the game camera. Only the camera's own transform and projection
+/// are touched, never anything gameplay reads.
+#[derive(Component, Default)]
+pub struct CameraFx {
+    trauma: f32,
+    zoom: f32,
+}
+
+#[derive(Resource, Default)]
+struct HitStop {
+    timer: Option<Timer>,
+}
+
+fn kicks(
+    mut events: EventReader<CameraKick>,
+    mut cameras: Query<&mut CameraFx>,
+    mut hit_stop: ResMut<HitStop>,
+    settings: Res<Settings>,
+) {
+    for event in events.iter() {
+        match *event {
+            CameraKick::Shake(amount) => {
+                for mut fx in cameras.iter_mut() {
+                    fx.trauma = (fx.trauma + amount).min(1.0);
+                }
+            }
+            CameraKick::ZoomPulse => {
+                for mut fx in cameras.iter_mut() {
+                    fx.zoom = 1.0;
+                }
+            }
+            // reduced motion: no freeze either
+            CameraKick::HitStop(_) if !settings.screen_shake => {}
+            CameraKick::HitStop(seconds) => {
+                hit_stop.timer = Some(Timer::new(
+                    Duration::from_secs_f32(seconds),
+                    TimerMode::Once,
+                ));
+            }
+        }
+    }
+
+    // reduced motion: keep the camera still
+    if !settings.screen_shake {
+        for mut fx in cameras.iter_mut() {
+            fx.trauma = 0.0;
+            fx.zoom = 0.0;
+        }
+    }
+}
+
+/// Runs on real time, since game time is what it stops.
+fn hit_stop(mut hit_stop: ResMut<HitStop>, mut time: ResMut<Time>) {
+    let raw = time.raw_delta();
+    let Some(timer) = &mut hit_stop.timer else {
+        return;
+    };
+
+    if timer.tick(raw).finished() {
+        hit_stop.timer = None;
+        time.unpause();
+    } else {
+        time.pause();
+    }
+}
+
+fn camera_fx(
+    mut cameras: Query<(&mut CameraFx, &mut Transform, &mut OrthographicProjection)>,
+    time: Res<Time>,
+) {
+    let dt = time.raw_delta_seconds();
+
+    for (mut fx, mut t, mut projection) in cameras.iter_mut() {
+        fx.trauma = (fx.trauma - TRAUMA_DECAY * dt).max(0.0);
+        fx.zoom = (fx.zoom - ZOOM_DECAY * dt).max(0.0);
+
+        let shake = fx.trauma * fx.trauma;
+        let noise = || rand::random::<f32>() * 2.0 - 1.0;
+        t.translation.x = MAX_OFFSET * shake * noise();
+        t.translation.y = MAX_OFFSET * shake * noise();
+        t.rotation = Quat::from_rotation_z(MAX_ROLL * shake * noise());
+
+        projection.scale = 1.0 - ZOOM_AMOUNT * fx.zoom * fx.zoom;
+    }
+}
+
+fn cleanup(mut hit_stop: ResMut<HitStop>, mut time: ResMut<Time>) {
+    hit_stop.timer = None;
+    time.unpause();
+}
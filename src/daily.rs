@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{bee_game::GameInfo, modes::GameMode, storage, GameState};
+
+const DAILY_FILE: &str = "daily.ron";
+
+/// How many past days the menu lists.
+const HISTORY_SHOWN: usize = 5;
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DailyHistory::load())
+            .add_system(start_run.in_schedule(OnEnter(GameState::Game)))
+            .add_system(record_run.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+/// Today's challenge. Days follow the player's own calendar, so a new
+/// challenge comes in at their midnight.
+pub struct Day {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub seed: u64,
+}
+
+pub fn today() -> Day {
+    let date = Local::now().date_naive();
+    let days = date.signed_duration_since(NaiveDate::default()).num_days();
+
+    Day {
+        date: date.format("%Y-%m-%d").to_string(),
+        // spread neighbouring days far apart
+        seed: (days as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+    }
+}
+
+/// Score of the first attempt at each day's challenge, keyed by date.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct DailyHistory {
+    results: BTreeMap<String, u32>,
+}
+
+impl DailyHistory {
+    pub fn load() -> Self {
+        storage::load(DAILY_FILE)
+    }
+
+    pub fn save(&self) {
+        storage::save(DAILY_FILE, self);
+    }
+
+    pub fn played(&self, date: &str) -> bool {
+        self.results.contains_key(date)
+    }
+
+    /// Most recent first.
+    pub fn recent(&self) -> impl Iterator<Item = (&String, &u32)> {
+        self.results.iter().rev().take(HISTORY_SHOWN)
+    }
+}
+
+/// Present during a daily run. Only the first attempt of the day counts,
+/// later ones are practice.
+#[derive(Resource)]
+pub struct DailyRun {
+    pub date: String,
+    pub counts: bool,
+}
+
+fn start_run(mut commands: Commands, mode: Res<GameMode>, mut history: ResMut<DailyHistory>) {
+    commands.remove_resource::<DailyRun>();
+    if *mode != GameMode::Daily {
+        return;
+    }
+
+    let day = today();
+    let counts = !history.played(&day.date);
+    if counts {
+        // claim the attempt straight away, so quitting out doesn't reset it
+        history.results.insert(day.date.clone(), 0);
+        history.save();
+    }
+    commands.insert_resource(DailyRun {
+        date: day.date,
+        counts,
+    });
+}
+
+fn record_run(
+    run: Option<Res<DailyRun>>,
+    game_info: Res<GameInfo>,
+    mut history: ResMut<DailyHistory>,
+) {
+    let Some(run) = run else {
+        return;
+    };
+    if run.counts {
+        history.results.insert(run.date.clone(), game_info.score);
+        history.save();
+    }
+}
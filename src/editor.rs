@@ -0,0 +1,508 @@
+use std::{collections::HashMap, fs};
+
+use bevy::{
+    asset::FileAssetIo,
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use bevy_prototype_debug_lines::DebugLines;
+
+use crate::{
+    bee_game::{setup_pillars, PillarShared},
+    levels::{spawn_obstacle, Level, Levels, Obstacle, Playtest, SelectedLevel, LEVELS},
+    modes::GameMode,
+    settings::Settings,
+    sim::{BEE_X, GAP_BOTTOM, GAP_TOP},
+    view, GameState,
+};
+
+/// Seconds the timeline scrolls per second of holding an arrow key.
+const SCROLL_SPEED: f32 = 4.0;
+/// Obstacle times snap to this many seconds.
+const SNAP: f32 = 0.1;
+/// How far either side of a pillar, in pixels, a click still picks it.
+const PICK_WIDTH: f32 = 40.0;
+const GAP_LIMIT: f32 = 200.0;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(setup.in_schedule(OnEnter(GameState::Editor)))
+            .add_systems(
+                (
+                    scroll,
+                    mouse_edit,
+                    key_edit,
+                    rebuild,
+                    apply_system_buffers,
+                    place,
+                    draw_route,
+                    hud,
+                )
+                    .chain()
+                    .in_set(OnUpdate(GameState::Editor)),
+            )
+            .add_system(cleanup.in_schedule(OnExit(GameState::Editor)))
+            .add_system(return_from_playtest.in_base_set(CoreSet::PostUpdate));
+    }
+}
+
+/// The level being edited. Kept across playtests, and only written back to
+/// the level file when saved.
+#[derive(Resource)]
+struct Editing {
+    index: usize,
+    level: Level,
+    /// Point on the timeline being looked at, in seconds since the level
+    /// started.
+    time: f32,
+    selected: Option<usize>,
+    dragging: bool,
+    saved: bool,
+    /// Unsaved edits to the other levels, picked up again on tabbing back.
+    drafts: HashMap<usize, Level>,
+}
+
+impl Editing {
+    fn new(index: usize, level: Level) -> Self {
+        Self {
+            index,
+            level,
+            time: 0.0,
+            selected: None,
+            dragging: false,
+            saved: true,
+            drafts: HashMap::new(),
+        }
+    }
+
+    fn sort(&mut self) {
+        let selected = self.selected.map(|i| self.level.obstacles[i].at);
+        self.level.obstacles.sort_by(|a, b| a.at.total_cmp(&b.at));
+        self.selected =
+            selected.and_then(|at| self.level.obstacles.iter().position(|o| o.at == at));
+    }
+}
+
+/// Where an obstacle `at` seconds into the level is on screen at `time`,
+/// matching how far the real pillars would have travelled.
+fn obstacle_x(pillar_shared: &PillarShared, at: f32, time: f32) -> f32 {
+    pillar_shared.spawn_x() + (time - at) * pillar_shared.x_vel
+}
+
+fn obstacle_at(pillar_shared: &PillarShared, x: f32, time: f32) -> f32 {
+    let at = time - (x - pillar_shared.spawn_x()) / pillar_shared.x_vel;
+    (at / SNAP).round() * SNAP
+}
+
+#[derive(Component)]
+struct EditorMarker;
+
+#[derive(Component)]
+struct EditorPillar(usize);
+
+#[derive(Component)]
+struct EditorText;
+
+#[allow(clippy::too_many_arguments)]
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    wins: Query<&Window>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    editing: Option<Res<Editing>>,
+    selected: Res<SelectedLevel>,
+    levels: Res<Levels>,
+    mut level_assets: ResMut<Assets<Level>>,
+    playtest: Option<Res<Playtest>>,
+    settings: Res<Settings>,
+) {
+    if let Some(playtest) = playtest {
+        level_assets.remove(&playtest.0);
+        commands.remove_resource::<Playtest>();
+    }
+
+    setup_pillars(
+        &mut commands,
+        &asset_server,
+        &wins,
+        &mut texture_atlases,
+        GameMode::Level,
+    );
+
+    // back from a playtest, carry on where we left off
+    if editing.is_none() {
+        let level = level_assets
+            .get(&levels.handles[selected.index])
+            .cloned()
+            .unwrap_or_else(|| Level {
+                name: "Untitled".to_string(),
+                stars: (1, 2),
+                obstacles: Vec::new(),
+            });
+        commands.insert_resource(Editing::new(selected.index, level));
+    }
+
+    commands.spawn((
+        Camera2dBundle {
+            camera_2d: Camera2d {
+                clear_color: bevy::core_pipeline::clear_color::ClearColorConfig::Custom(
+                    settings.palette.sky(),
+                ),
+            },
+            projection: view::projection(),
+            ..default()
+        },
+        EditorMarker,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/goodtimes.otf"),
+                font_size: 18.0,
+                color: settings.palette.button_text(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        }),
+        EditorText,
+        EditorMarker,
+    ));
+}
+
+fn scroll(
+    mut editing: ResMut<Editing>,
+    keys: Res<Input<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    time: Res<Time>,
+) {
+    let mut delta = 0.0;
+    if keys.pressed(KeyCode::Right) {
+        delta += SCROLL_SPEED * time.delta_seconds();
+    }
+    if keys.pressed(KeyCode::Left) {
+        delta -= SCROLL_SPEED * time.delta_seconds();
+    }
+    for event in wheel.iter() {
+        delta += match event.unit {
+            MouseScrollUnit::Line => event.y * 0.5,
+            MouseScrollUnit::Pixel => event.y * 0.01,
+        };
+    }
+
+    if delta != 0.0 {
+        editing.time = (editing.time + delta).max(0.0);
+    }
+}
+
+fn mouse_edit(
+    mut editing: ResMut<Editing>,
+    buttons: Res<Input<MouseButton>>,
+    wins: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<EditorMarker>>,
+    pillar_shared: Res<PillarShared>,
+) {
+    let Ok((camera, camera_t)) = cameras.get_single() else {
+        return;
+    };
+    let Some(cursor) = wins
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .and_then(|pos| camera.viewport_to_world_2d(camera_t, pos))
+    else {
+        return;
+    };
+    let gap = cursor.y.round().clamp(-GAP_LIMIT, GAP_LIMIT);
+    let time = editing.time;
+
+    if buttons.just_pressed(MouseButton::Left) {
+        let picked =
+            editing.level.obstacles.iter().position(|o| {
+                (obstacle_x(&pillar_shared, o.at, time) - cursor.x).abs() < PICK_WIDTH
+            });
+        editing.selected = picked;
+        editing.dragging = picked.is_some();
+    }
+
+    if buttons.just_pressed(MouseButton::Right) {
+        editing.level.obstacles.push(Obstacle {
+            at: obstacle_at(&pillar_shared, cursor.x, time).max(0.0),
+            gap,
+            kind: default(),
+            pickup: false,
+            checkpoint: false,
+        });
+        editing.selected = Some(editing.level.obstacles.len() - 1);
+        editing.saved = false;
+        editing.sort();
+    }
+
+    if editing.dragging {
+        if let Some(i) = editing.selected {
+            let at = obstacle_at(&pillar_shared, cursor.x, time).max(0.0);
+            let obstacle = &editing.level.obstacles[i];
+            if obstacle.at != at || obstacle.gap != gap {
+                let obstacle = &mut editing.level.obstacles[i];
+                obstacle.at = at;
+                obstacle.gap = gap;
+                editing.saved = false;
+            }
+        }
+        if buttons.just_released(MouseButton::Left) {
+            editing.dragging = false;
+            editing.sort();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn key_edit(
+    mut commands: Commands,
+    mut editing: ResMut<Editing>,
+    keys: Res<Input<KeyCode>>,
+    levels: Res<Levels>,
+    mut level_assets: ResMut<Assets<Level>>,
+    mut selected: ResMut<SelectedLevel>,
+    mut mode: ResMut<GameMode>,
+    mut game_state: ResMut<NextState<GameState>>,
+    pillar_shared: Res<PillarShared>,
+) {
+    if let Some(i) = editing.selected {
+        // edit a copy, so the level only counts as changed when it has
+        let mut obstacle = editing.level.obstacles[i].clone();
+        if keys.just_pressed(KeyCode::Up) {
+            obstacle.gap = (obstacle.gap + 10.0).min(GAP_LIMIT);
+        } else if keys.just_pressed(KeyCode::Down) {
+            obstacle.gap = (obstacle.gap - 10.0).max(-GAP_LIMIT);
+        } else if keys.just_pressed(KeyCode::G) {
+            obstacle.kind = obstacle.kind.next();
+        } else if keys.just_pressed(KeyCode::P) {
+            obstacle.pickup = !obstacle.pickup;
+        } else if keys.just_pressed(KeyCode::C) {
+            obstacle.checkpoint = !obstacle.checkpoint;
+        }
+
+        if keys.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+            editing.level.obstacles.remove(i);
+            editing.selected = None;
+            editing.saved = false;
+        } else if obstacle != editing.level.obstacles[i] {
+            editing.level.obstacles[i] = obstacle;
+            editing.saved = false;
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Tab) {
+        let index = (editing.index + 1) % levels.handles.len();
+        let draft = editing.drafts.remove(&index);
+        let level = draft
+            .clone()
+            .or_else(|| level_assets.get(&levels.handles[index]).cloned());
+        if let Some(level) = level {
+            let mut drafts = std::mem::take(&mut editing.drafts);
+            if !editing.saved {
+                drafts.insert(editing.index, editing.level.clone());
+            }
+            *editing = Editing {
+                saved: draft.is_none(),
+                drafts,
+                ..Editing::new(index, level)
+            };
+        }
+    }
+
+    if keys.just_pressed(KeyCode::S) && save(&editing) {
+        // levels aren't hot reloaded, so keep the loaded copy in step
+        if let Some(level) = level_assets.get_mut(&levels.handles[editing.index]) {
+            *level = editing.level.clone();
+        }
+        editing.saved = true;
+    }
+
+    if keys.just_pressed(KeyCode::Return) {
+        // start from the selected obstacle, or whichever is coming up next
+        let bee_time = editing.time - (BEE_X - pillar_shared.spawn_x()) / pillar_shared.x_vel;
+        let start = editing.selected.unwrap_or_else(|| {
+            editing
+                .level
+                .obstacles
+                .iter()
+                .position(|o| o.at >= bee_time)
+                .unwrap_or(0)
+        });
+
+        *selected = SelectedLevel {
+            index: editing.index,
+            start,
+        };
+        *mode = GameMode::Level;
+        // play the working copy, not what's on disk
+        commands.insert_resource(Playtest(level_assets.add(editing.level.clone())));
+        game_state.set(GameState::Game);
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<Editing>();
+        game_state.set(GameState::Menu);
+    }
+}
+
+fn save(editing: &Editing) -> bool {
+    let path = FileAssetIo::get_base_path()
+        .join("assets")
+        .join(LEVELS[editing.index]);
+    let pretty = ron::ser::PrettyConfig::new().struct_names(false);
+    match ron::ser::to_string_pretty(&editing.level, pretty) {
+        Ok(text) => match fs::write(&path, text) {
+            Ok(()) => {
+                info!("saved level {}", path.display());
+                return true;
+            }
+            Err(e) => error!("couldn't save level {}: {}", path.display(), e),
+        },
+        Err(e) => error!("couldn't serialize level: {}", e),
+    }
+    false
+}
+
+/// Respawns the pillars whenever the level changes shape.
+fn rebuild(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    editing: Res<Editing>,
+    pillars: Query<Entity, With<EditorPillar>>,
+    pillar_shared: Res<PillarShared>,
+    mut built: Local<Option<Level>>,
+) {
+    // scrolling alone only moves them, see `place`
+    let spawned = pillars.iter().count() == editing.level.obstacles.len();
+    if spawned && built.as_ref() == Some(&editing.level) {
+        return;
+    }
+    *built = Some(editing.level.clone());
+
+    for e in pillars.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+    for (i, obstacle) in editing.level.obstacles.iter().enumerate() {
+        let e = spawn_obstacle(&mut commands, &asset_server, &pillar_shared, obstacle);
+        commands.entity(e).insert((EditorPillar(i), EditorMarker));
+    }
+}
+
+fn place(
+    editing: Res<Editing>,
+    mut pillars: Query<(&mut Transform, &mut Sprite, &EditorPillar)>,
+    pillar_shared: Res<PillarShared>,
+) {
+    for (mut t, mut sprite, p) in pillars.iter_mut() {
+        let Some(obstacle) = editing.level.obstacles.get(p.0) else {
+            continue;
+        };
+        t.translation.x = obstacle_x(&pillar_shared, obstacle.at, editing.time);
+        t.translation.y = obstacle.gap;
+        sprite.color = match editing.selected == Some(p.0) {
+            true => Color::rgb(1.5, 1.3, 0.6),
+            false => Color::WHITE,
+        };
+    }
+}
+
+/// Draws the path through each gap, and the line the bee flies along.
+fn draw_route(
+    editing: Res<Editing>,
+    pillars: Query<&Transform, With<EditorPillar>>,
+    mut debug_lines: ResMut<DebugLines>,
+) {
+    let mut gaps: Vec<Vec3> = pillars
+        .iter()
+        .map(|t| {
+            let centre = t.translation.y + (GAP_TOP + GAP_BOTTOM) / 2.0 * t.scale.y;
+            Vec3::new(t.translation.x, centre, 500.0)
+        })
+        .collect();
+    gaps.sort_by(|a, b| a.x.total_cmp(&b.x));
+    for pair in gaps.windows(2) {
+        debug_lines.line_colored(pair[0], pair[1], 0.0, Color::YELLOW);
+    }
+
+    let top = Vec3::new(BEE_X, view::VIRTUAL_HEIGHT / 2.0, 500.0);
+    let bottom = Vec3::new(BEE_X, -view::VIRTUAL_HEIGHT / 2.0, 500.0);
+    let colour = match editing.saved {
+        true => Color::GREEN,
+        false => Color::ORANGE,
+    };
+    debug_lines.line_colored(top, bottom, 0.0, colour);
+}
+
+fn hud(
+    editing: Res<Editing>,
+    mut texts: Query<&mut Text, With<EditorText>>,
+    fresh: Query<(), Added<EditorText>>,
+) {
+    // the text is spawned again on the way back from a playtest
+    if !editing.is_changed() && fresh.is_empty() {
+        return;
+    }
+
+    let mut status = format!(
+        "{}{}  {:.1}s  {} obstacles",
+        editing.level.name,
+        if editing.saved { "" } else { " *" },
+        editing.time,
+        editing.level.obstacles.len(),
+    );
+    if !editing.drafts.is_empty() {
+        status += &format!("  ({} other levels unsaved)", editing.drafts.len());
+    }
+    if let Some(o) = editing.selected.map(|i| &editing.level.obstacles[i]) {
+        status += &format!(
+            "\nat {:.1}s  gap {}  {:?}{}{}",
+            o.at,
+            o.gap,
+            o.kind,
+            if o.pickup { "  pickup" } else { "" },
+            if o.checkpoint { "  checkpoint" } else { "" },
+        );
+    }
+    status += "\n\nleft/right, wheel: scroll   click: select/drag   right click: add\n\
+               up/down: gap   G: gap size   P: pickup   C: checkpoint   del: remove\n\
+               enter: playtest   S: save   tab: next level   esc: exit";
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = status.clone();
+    }
+}
+
+/// Sends a playtest back to the editor rather than the menu, however it ends.
+fn return_from_playtest(
+    playtest: Option<Res<Playtest>>,
+    state: Res<State<GameState>>,
+    keys: Res<Input<KeyCode>>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    if playtest.is_none() || state.0 != GameState::Game {
+        return;
+    }
+    if next.0 == Some(GameState::Menu) || keys.just_pressed(KeyCode::Escape) {
+        next.set(GameState::Editor);
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<EditorMarker>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
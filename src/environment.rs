@@ -0,0 +1,215 @@
+use bevy::{
+    core_pipeline::{bloom::BloomSettings, clear_color::ClearColorConfig},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    bee_game::FlightClock,
+    biomes::Scenery,
+    particles::{ParticleKind, SpawnParticles},
+    settings::Settings,
+    sim::WeatherKind,
+    view, GameState,
+};
+
+/// Seconds for a full day and night.
+const DAY_LENGTH: f32 = 120.0;
+
+const RAIN_PER_SECOND: f32 = 90.0;
+/// Fraction of the screen, from the side pillars come in on, hidden by fog.
+const FOG_COVER: f32 = 0.55;
+
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Environment>()
+            .add_system(setup.in_schedule(OnEnter(GameState::Game)))
+            .add_system(day_cycle.in_set(OnUpdate(GameState::Game)))
+            .add_system(weather.in_set(OnUpdate(GameState::Game)))
+            .add_system(cleanup.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+#[derive(Resource)]
+pub struct Environment {
+    /// 0 is midday, 0.5 midnight.
+    pub time_of_day: f32,
+    /// Colour multiplied over the background layers.
+    pub tint: Color,
+    /// The run's weather, which is drawn from its seed and stepped with the
+    /// bee's flight so replays meet the same storms.
+    pub weather: WeatherKind,
+    rain: f32,
+    fog: f32,
+    rain_owed: f32,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            time_of_day: 0.0,
+            tint: Color::WHITE,
+            weather: WeatherKind::Clear,
+            rain: 0.0,
+            fog: 0.0,
+            rain_owed: 0.0,
+        }
+    }
+}
+
+#[derive(Component)]
+struct EnvironmentMarker;
+
+#[derive(Component)]
+struct Fog;
+
+struct Keyframe {
+    at: f32,
+    sky: Color,
+    tint: Color,
+    bloom: f32,
+}
+
+/// `sky` here multiplies the palette's, or the biome's, sky colour.
+const KEYFRAMES: &[Keyframe] = &[
+    Keyframe {
+        at: 0.0,
+        sky: Color::WHITE,
+        tint: Color::WHITE,
+        bloom: 0.15,
+    },
+    Keyframe {
+        at: 0.3,
+        sky: Color::WHITE,
+        tint: Color::WHITE,
+        bloom: 0.15,
+    },
+    // dusk
+    Keyframe {
+        at: 0.4,
+        sky: Color::rgb(2.2, 0.75, 0.45),
+        tint: Color::rgb(1.0, 0.8, 0.7),
+        bloom: 0.22,
+    },
+    // night
+    Keyframe {
+        at: 0.5,
+        sky: Color::rgb(0.15, 0.12, 0.25),
+        tint: Color::rgb(0.35, 0.4, 0.6),
+        bloom: 0.35,
+    },
+    Keyframe {
+        at: 0.75,
+        sky: Color::rgb(0.15, 0.12, 0.25),
+        tint: Color::rgb(0.35, 0.4, 0.6),
+        bloom: 0.35,
+    },
+    // dawn
+    Keyframe {
+        at: 0.88,
+        sky: Color::rgb(2.0, 0.8, 0.7),
+        tint: Color::rgb(0.95, 0.8, 0.8),
+        bloom: 0.2,
+    },
+    Keyframe {
+        at: 1.0,
+        sky: Color::WHITE,
+        tint: Color::WHITE,
+        bloom: 0.15,
+    },
+];
+
+pub fn mix(a: Color, b: Color, t: f32) -> Color {
+    Color::from(Vec4::from(a).lerp(Vec4::from(b), t))
+}
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(Environment::default());
+
+    // covers the side of the screen pillars come in from, faded in with fog
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.9, 0.92, 0.95, 0.0),
+                custom_size: Some(Vec2::ONE),
+                anchor: bevy::sprite::Anchor::CenterLeft,
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 90.0),
+            ..Default::default()
+        },
+        Fog,
+        EnvironmentMarker,
+    ));
+}
+
+fn day_cycle(
+    mut env: ResMut<Environment>,
+    mut cameras: Query<(&mut Camera2d, &mut BloomSettings)>,
+    scenery: Res<Scenery>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    env.time_of_day = (env.time_of_day + time.delta_seconds() / DAY_LENGTH).fract();
+
+    let t = env.time_of_day;
+    let i = KEYFRAMES.iter().position(|k| k.at > t).unwrap_or(1).max(1);
+    let (a, b) = (&KEYFRAMES[i - 1], &KEYFRAMES[i]);
+    let f = (t - a.at) / (b.at - a.at);
+
+    // rain and fog grey everything out a little
+    let gloom = 1.0 - 0.35 * env.rain.max(env.fog);
+    let tint = mix(a.tint, b.tint, f) * Vec4::from(scenery.tint()) * gloom;
+    env.tint = tint.with_a(1.0);
+
+    let sky = mix(a.sky, b.sky, f) * Vec4::from(scenery.sky(settings.palette.sky())) * gloom;
+    for (mut camera, mut bloom) in cameras.iter_mut() {
+        bloom.intensity = a.bloom + (b.bloom - a.bloom) * f;
+        // later split screen cameras draw over the first one's sky
+        if let ClearColorConfig::None = camera.clear_color {
+            continue;
+        }
+        camera.clear_color = ClearColorConfig::Custom(sky.with_a(1.0));
+    }
+}
+
+fn weather(
+    mut env: ResMut<Environment>,
+    clock: Res<FlightClock>,
+    mut fog: Query<(&mut Sprite, &mut Transform), With<Fog>>,
+    mut particles: EventWriter<SpawnParticles>,
+    wins: Query<&Window, With<PrimaryWindow>>,
+    time: Res<Time>,
+) {
+    // its pull on the bee is applied with each flight step
+    env.weather = clock.weather.kind;
+    env.rain = clock.weather.rain;
+    env.fog = clock.weather.fog;
+
+    let size = view::primary_visible_size(&wins);
+
+    // rain
+    env.rain_owed += RAIN_PER_SECOND * env.rain * time.delta_seconds();
+    while env.rain_owed >= 1.0 {
+        env.rain_owed -= 1.0;
+        particles.send(SpawnParticles {
+            kind: ParticleKind::Rain,
+            at: Vec2::new((rand::random::<f32>() - 0.5) * size.x, size.y / 2.0 + 20.0),
+        });
+    }
+
+    // fog
+    for (mut sprite, mut t) in fog.iter_mut() {
+        sprite.color.set_a(0.9 * env.fog);
+        t.translation.x = -size.x / 2.0;
+        t.scale = Vec3::new(size.x * FOG_COVER, size.y, 1.0);
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<EnvironmentMarker>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -0,0 +1,341 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::{Animator, Clip},
+    bee_game::{Flapped, GameInfo, PillarShared, Player},
+    daily,
+    levels::{Playtest, SelectedLevel, LEVELS},
+    modes::GameMode,
+    settings::{Controls, Settings},
+    sim::{Sample, Submission, BEE_X, SAMPLE_EVERY},
+    skins::Dressed,
+    storage, GameState,
+};
+
+pub const GHOST_ALPHA: f32 = 0.35;
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(start_race.in_schedule(OnEnter(GameState::Game)))
+            .add_systems((record, replay).chain().in_set(OnUpdate(GameState::Game)))
+            .add_system(keep_best.in_schedule(OnExit(GameState::Game)))
+            .add_system(cleanup.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+/// A recorded run, kept per mode and per level to race against.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+struct Ghost {
+    /// Seed of the pillars the run was flown through.
+    seed: u64,
+    score: u32,
+    /// Where pillars came in, which depends on the window's shape.
+    spawn_x: f32,
+    /// Seconds into the run each flap came at.
+    flaps: Vec<f32>,
+    /// Positions every `SAMPLE_EVERY` seconds.
+    samples: Vec<Sample>,
+}
+
+impl Ghost {
+    fn load(file: &str) -> Option<Self> {
+        let ghost: Ghost = storage::load(file);
+        (!ghost.samples.is_empty()).then_some(ghost)
+    }
+
+    /// Where the ghost was `secs` into its run, or `None` once it's over.
+    fn at(&self, secs: f32) -> Option<Sample> {
+        let i = secs / SAMPLE_EVERY;
+        let a = self.samples.get(i as usize)?;
+        let b = self.samples.get(i as usize + 1)?;
+        Some(a.lerp(*b, i.fract()))
+    }
+}
+
+/// Save file for the ghost of a mode, and of a level in level mode. Versus
+/// and online races don't keep one.
+fn file(mode: GameMode, level: usize) -> Option<String> {
+    match mode {
+        GameMode::Versus | GameMode::Online => None,
+        GameMode::Level => {
+            let name = Path::new(LEVELS[level]).file_name()?.to_str()?;
+            let stem = name.split('.').next()?;
+            Some(format!("ghost-{}.ron", stem))
+        }
+        _ => Some(format!("ghost-{:?}.ron", mode).to_lowercase()),
+    }
+}
+
+/// Seed for a run's pillars. Daily runs get the day's, and everything else a
+/// fresh one unless racing the ghost is turned on, which replays the ghost's
+/// pillars so there's something to race.
+pub fn seed(mode: GameMode, settings: &Settings) -> u64 {
+    match mode {
+        GameMode::Daily => daily::today().seed,
+        GameMode::Level | GameMode::Versus | GameMode::Online => rand::random(),
+        _ => settings
+            .ghost
+            .then(|| file(mode, 0))
+            .flatten()
+            .and_then(|file| Ghost::load(&file))
+            .map_or_else(rand::random, |ghost| ghost.seed),
+    }
+}
+
+/// The run in progress, saved over the ghost if it does better.
+#[derive(Resource)]
+pub struct Recording {
+    file: String,
+    ghost: Ghost,
+    elapsed: f32,
+    classic: bool,
+}
+
+impl Recording {
+    /// The run as a leaderboard entry, replay and all.
+    pub fn submission(&self, name: &str, mode: String, game_info: &GameInfo) -> Submission {
+        Submission {
+            name: name.to_string(),
+            mode,
+            seed: game_info.seed,
+            score: game_info.score,
+            spawn_x: self.ghost.spawn_x,
+            classic: self.classic,
+            flaps: self.ghost.flaps.clone(),
+            samples: self.ghost.samples.clone(),
+        }
+    }
+}
+
+/// The ghost being raced.
+#[derive(Resource)]
+struct Race {
+    ghost: Ghost,
+    next_flap: usize,
+    checked: bool,
+}
+
+#[derive(Component)]
+struct GhostMarker;
+
+#[derive(Component)]
+struct GhostBee;
+
+/// How far ahead of or behind the ghost the player is, in pillars passed
+/// by the same moment of the run.
+#[derive(Component)]
+struct GhostLabel;
+
+fn start_race(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mode: Res<GameMode>,
+    selected: Res<SelectedLevel>,
+    playtest: Option<Res<Playtest>>,
+    settings: Res<Settings>,
+) {
+    commands.remove_resource::<Recording>();
+    commands.remove_resource::<Race>();
+
+    // playtests can start part way in, which isn't a run worth keeping
+    if playtest.is_some() {
+        return;
+    }
+    let Some(file) = file(*mode, selected.index) else {
+        return;
+    };
+    let ghost = Ghost::load(&file).filter(|_| settings.ghost);
+    commands.insert_resource(Recording {
+        file,
+        ghost: Ghost::default(),
+        elapsed: 0.0,
+        classic: settings.controls == Controls::Classic,
+    });
+
+    let Some(ghost) = ghost else {
+        return;
+    };
+    commands.insert_resource(Race {
+        ghost,
+        next_flap: 0,
+        checked: false,
+    });
+
+    commands.spawn((
+        SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                custom_size: Some(Vec2::splat(80.0)),
+                color: Color::rgba(1.0, 1.0, 1.0, GHOST_ALPHA),
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(BEE_X, 0.0, 95.0),
+            ..Default::default()
+        },
+        // it's the player's own run, so it wears their skin
+        Dressed,
+        Animator::new(Clip::Idle),
+        GhostBee,
+        GhostMarker,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/goodtimes.otf"),
+                    font_size: 24.0,
+                    color: settings.palette.score_text().with_a(0.7),
+                },
+            ),
+            transform: Transform::from_xyz(BEE_X, 60.0, 131.0),
+            ..Default::default()
+        },
+        GhostLabel,
+        GhostMarker,
+    ));
+}
+
+fn record(
+    recording: Option<ResMut<Recording>>,
+    mut flapped: EventReader<Flapped>,
+    players: Query<(&Transform, &Player)>,
+    pillar_shared: Res<PillarShared>,
+    game_info: Res<GameInfo>,
+    time: Res<Time>,
+) {
+    let Some(mut recording) = recording else {
+        return;
+    };
+    let Some((t, player)) = players.iter().find(|(_, p)| p.index == 0) else {
+        return;
+    };
+
+    recording.elapsed += time.delta_seconds();
+    let elapsed = recording.elapsed;
+    let ghost = &mut recording.ghost;
+    if ghost.samples.is_empty() {
+        ghost.spawn_x = pillar_shared.spawn_x();
+    }
+
+    for event in flapped.iter() {
+        if event.player == 0 {
+            ghost.flaps.push(event.at);
+        }
+    }
+
+    while ghost.samples.len() as f32 * SAMPLE_EVERY <= elapsed {
+        ghost.samples.push(Sample {
+            x: t.translation.x,
+            y: t.translation.y,
+            world: player.y_pos,
+            score: game_info.score,
+        });
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn replay(
+    mut commands: Commands,
+    race: Option<ResMut<Race>>,
+    recording: Option<Res<Recording>>,
+    game_info: Res<GameInfo>,
+    mode: Res<GameMode>,
+    players: Query<&Player>,
+    mut ghosts: Query<(&mut Transform, &mut Animator, &mut Visibility), With<GhostBee>>,
+    mut labels: Query<(&mut Transform, &mut Text), (With<GhostLabel>, Without<GhostBee>)>,
+    ghost_entities: Query<Entity, With<GhostMarker>>,
+) {
+    let (Some(mut race), Some(recording)) = (race, recording) else {
+        return;
+    };
+
+    // a ghost is only worth racing through the same pillars, which a daily
+    // ghost from another day isn't
+    if !race.checked {
+        race.checked = true;
+        if *mode != GameMode::Level && race.ghost.seed != game_info.seed {
+            commands.remove_resource::<Race>();
+            for e in ghost_entities.iter() {
+                commands.entity(e).despawn_recursive();
+            }
+            return;
+        }
+    }
+
+    let elapsed = recording.elapsed;
+    let world = players
+        .iter()
+        .find(|p| p.index == 0)
+        .map_or(0.0, |p| p.y_pos);
+
+    let flapped = race
+        .ghost
+        .flaps
+        .get(race.next_flap)
+        .is_some_and(|&at| at <= elapsed);
+    if flapped {
+        race.next_flap += 1;
+    }
+
+    let sample = race.ghost.at(elapsed);
+    for (mut t, mut anim, mut visibility) in ghosts.iter_mut() {
+        let Some(sample) = sample else {
+            // the ghost's run ended here
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        // the ghost's world sits at a different height, so shift it into ours
+        t.translation.x = sample.x;
+        t.translation.y = sample.y - sample.world + world;
+        if flapped {
+            anim.play(Clip::Flap);
+        }
+    }
+
+    // once the ghost's run is over, it stays at its final score
+    let ghost_score = sample.map_or(race.ghost.score, |s| s.score);
+    let lead = game_info.score as i64 - ghost_score as i64;
+    for (mut t, mut text) in labels.iter_mut() {
+        if let Some(sample) = sample {
+            t.translation.x = sample.x;
+            t.translation.y = sample.y - sample.world + world + 60.0;
+        }
+        text.sections[0].value = format!("{:+}", lead);
+    }
+}
+
+fn keep_best(recording: Option<Res<Recording>>, game_info: Res<GameInfo>, mode: Res<GameMode>) {
+    let Some(recording) = recording else {
+        return;
+    };
+    if recording.ghost.samples.is_empty() {
+        return;
+    }
+
+    let better = match Ghost::load(&recording.file) {
+        // a daily ghost from another day is stale whatever it scored
+        Some(old) if *mode == GameMode::Daily && old.seed != game_info.seed => true,
+        Some(old) => game_info.score > old.score,
+        None => true,
+    };
+    if better {
+        let ghost = Ghost {
+            seed: game_info.seed,
+            score: game_info.score,
+            ..recording.ghost.clone()
+        };
+        storage::save(&recording.file, &ghost);
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<GhostMarker>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
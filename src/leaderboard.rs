@@ -0,0 +1,268 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    bee_game::GameInfo,
+    daily::{self, DailyRun},
+    ghost::Recording,
+    modes::GameMode,
+    settings::Settings,
+    sim::{self, Entry, Receipt, Verdict},
+    GameState,
+};
+
+/// Entries listed on the menu.
+const SHOWN: usize = 5;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Leaderboard>()
+            .add_system(submit.in_schedule(OnExit(GameState::Game)))
+            .add_system(fetch.in_schedule(OnEnter(GameState::Menu)))
+            .add_system(
+                fetch
+                    .run_if(resource_changed::<GameMode>())
+                    .in_set(OnUpdate(GameState::Menu)),
+            )
+            .add_system(receive)
+            .add_system(panel_setup.in_schedule(OnEnter(GameState::Menu)))
+            .add_system(panel_update.in_set(OnUpdate(GameState::Menu)))
+            .add_system(cleanup.in_schedule(OnExit(GameState::Menu)));
+    }
+}
+
+/// Sent back from the threads talking to the server.
+enum Reply {
+    Top { board: String, entries: Vec<Entry> },
+    Submitted { board: String, receipt: Receipt },
+    Failed,
+}
+
+/// What's known of the current mode's leaderboard.
+#[derive(Resource)]
+struct Leaderboard {
+    board: Option<String>,
+    top: Vec<Entry>,
+    /// How the last run submitted went.
+    verdict: Option<Verdict>,
+    offline: bool,
+    sender: Sender<Reply>,
+    replies: Mutex<Receiver<Reply>>,
+}
+
+impl Default for Leaderboard {
+    fn default() -> Self {
+        let (sender, replies) = mpsc::channel();
+        Self {
+            board: None,
+            top: Vec::new(),
+            verdict: None,
+            offline: false,
+            sender,
+            replies: Mutex::new(replies),
+        }
+    }
+}
+
+/// Just enough HTTP/1.1 to talk to the leaderboard, returning the body of a
+/// successful response.
+fn request(server: &str, method: &str, path: &str, body: &str) -> io::Result<String> {
+    let addr = server
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/ron\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        server,
+        body.len(),
+        body
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no body"))?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split(' ').nth(1) != Some("200") {
+        return Err(io::Error::other(status.to_string()));
+    }
+    Ok(body.to_string())
+}
+
+/// Runs `f` off the main thread, sending back what it returns.
+fn in_background(leaderboard: &Leaderboard, f: impl FnOnce() -> Reply + Send + 'static) {
+    let sender = leaderboard.sender.clone();
+    thread::spawn(move || {
+        // the game may have closed by the time this finishes
+        let _ = sender.send(f());
+    });
+}
+
+fn fetch(mode: Res<GameMode>, settings: Res<Settings>, mut leaderboard: ResMut<Leaderboard>) {
+    leaderboard.top.clear();
+    let Some(name) = mode.ranked() else {
+        leaderboard.board = None;
+        return;
+    };
+    let board = sim::board(&name, daily::today().seed);
+    leaderboard.board = Some(board.clone());
+
+    if settings.leaderboard.is_empty() {
+        return;
+    }
+    let server = settings.leaderboard.clone();
+    in_background(&leaderboard, move || {
+        request(&server, "GET", &format!("/scores/{}", board), "")
+            .ok()
+            .and_then(|body| ron::from_str(&body).ok())
+            .map_or(Reply::Failed, |entries| Reply::Top { board, entries })
+    });
+}
+
+fn submit(
+    recording: Option<Res<Recording>>,
+    game_info: Res<GameInfo>,
+    mode: Res<GameMode>,
+    daily: Option<Res<DailyRun>>,
+    settings: Res<Settings>,
+    mut leaderboard: ResMut<Leaderboard>,
+) {
+    leaderboard.verdict = None;
+    let (Some(recording), Some(name)) = (recording, mode.ranked()) else {
+        return;
+    };
+    // only the first go at a daily counts
+    if settings.leaderboard.is_empty() || game_info.score == 0 || daily.is_some_and(|d| !d.counts) {
+        return;
+    }
+
+    let run = recording.submission(&settings.name, name, &game_info);
+    // no point sending what the server would only turn away
+    match sim::verify(&run) {
+        Ok(score) if score >= run.score => (),
+        _ => {
+            leaderboard.verdict = Some(Verdict::Rejected("failed verification".to_string()));
+            return;
+        }
+    }
+
+    let Ok(body) = ron::to_string(&run) else {
+        return;
+    };
+    let board = sim::board(&run.mode, run.seed);
+    let server = settings.leaderboard.clone();
+    in_background(&leaderboard, move || {
+        request(&server, "POST", "/scores", &body)
+            .ok()
+            .and_then(|body| ron::from_str(&body).ok())
+            .map_or(Reply::Failed, |receipt| Reply::Submitted { board, receipt })
+    });
+}
+
+fn receive(mut leaderboard: ResMut<Leaderboard>) {
+    let replies: Vec<Reply> = match leaderboard.replies.lock() {
+        Ok(replies) => replies.try_iter().collect(),
+        Err(_) => return,
+    };
+
+    for reply in replies {
+        match reply {
+            Reply::Top { board, entries } => {
+                if leaderboard.board.as_ref() == Some(&board) {
+                    leaderboard.top = entries;
+                }
+                leaderboard.offline = false;
+            }
+            Reply::Submitted { board, receipt } => {
+                if leaderboard.board.as_ref() == Some(&board) {
+                    leaderboard.top = receipt.top;
+                }
+                leaderboard.verdict = Some(receipt.verdict);
+                leaderboard.offline = false;
+            }
+            Reply::Failed => leaderboard.offline = true,
+        }
+    }
+}
+
+#[derive(Component)]
+struct LeaderboardPanel;
+
+fn panel_setup(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/goodtimes.otf"),
+                font_size: 22.0,
+                color: settings.palette.button_text(),
+            },
+        )
+        // the last run's score takes the top left
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(20.0),
+                left: Val::Px(20.0),
+                ..default()
+            },
+            ..default()
+        }),
+        LeaderboardPanel,
+    ));
+}
+
+fn panel_update(
+    leaderboard: Res<Leaderboard>,
+    settings: Res<Settings>,
+    mut panels: Query<&mut Text, With<LeaderboardPanel>>,
+) {
+    let lang = settings.language;
+    let mut value = String::new();
+    if leaderboard.board.is_some() {
+        value += lang.tr("Leaderboard");
+        if leaderboard.offline {
+            value += &format!("\n{}", lang.tr("Offline"));
+        }
+        for (i, entry) in leaderboard.top.iter().take(SHOWN).enumerate() {
+            value += &format!("\n{}. {}  {}", i + 1, entry.name, entry.score);
+        }
+        match &leaderboard.verdict {
+            Some(Verdict::Ranked(rank)) => value += &format!("\n\n{} {}", lang.tr("Rank"), rank),
+            Some(Verdict::Rejected(_)) => value += &format!("\n\n{}", lang.tr("Not verified")),
+            Some(Verdict::Unranked) | None => (),
+        }
+    }
+
+    for mut text in panels.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<LeaderboardPanel>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
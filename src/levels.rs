@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bee_game::{spawn_piller, BeeFly, GameInfo, PillarShared},
+    modes::GameMode,
+    particles::{ParticleKind, SpawnParticles},
+    sfx::{PlaySfx, SoundEffect},
+    sim::{GAP_BOTTOM, GAP_TOP, PILLAR_SCALE},
+    stats::Tally,
+    storage, GameState,
+};
+
+const PROGRESS_FILE: &str = "levels.ron";
+
+/// In the order they're listed on the level select screen.
+pub const LEVELS: &[&str] = &[
+    "levels/01_meadow.level.ron",
+    "levels/02_orchard.level.ron",
+    "levels/03_hive.level.ron",
+];
+
+/// Seconds of warning before the obstacle after a checkpoint comes back in.
+const RESUME_LEAD: f32 = 2.0;
+/// How close, in pixels, the bee has to get to a pickup to collect it.
+const PICKUP_RADIUS: f32 = 45.0;
+
+pub struct LevelsPlugin;
+
+impl Plugin for LevelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .insert_resource(LevelProgress::load())
+            .add_startup_system(load_levels)
+            .add_system(start_run.in_schedule(OnEnter(GameState::Game)))
+            .add_system(level_spawner.in_set(OnUpdate(GameState::Game)))
+            .add_system(pickups.in_set(OnUpdate(GameState::Game)))
+            .add_system(level_progress.in_set(OnUpdate(GameState::Game)));
+    }
+}
+
+#[derive(Serialize, Deserialize, TypeUuid, Clone, Debug, PartialEq)]
+#[uuid = "9a3c6f1e-52d4-4b8a-a7e1-3f0d2c8b6e45"]
+pub struct Level {
+    pub name: String,
+    /// Pickups needed for two and three stars. Finishing is worth one.
+    pub stars: (u32, u32),
+    pub obstacles: Vec<Obstacle>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Obstacle {
+    /// Seconds from the start of the level.
+    pub at: f32,
+    /// Vertical offset of the gap, as with random pillars.
+    pub gap: f32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub kind: ObstacleKind,
+    /// Nectar hanging in the gap.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub pickup: bool,
+    /// Dying after passing this obstacle restarts from the next one.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub checkpoint: bool,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ObstacleKind {
+    #[default]
+    Pipe,
+    Narrow,
+    Wide,
+}
+
+impl ObstacleKind {
+    pub fn next(self) -> Self {
+        match self {
+            ObstacleKind::Pipe => ObstacleKind::Narrow,
+            ObstacleKind::Narrow => ObstacleKind::Wide,
+            ObstacleKind::Wide => ObstacleKind::Pipe,
+        }
+    }
+
+    fn gap_scale(self) -> f32 {
+        match self {
+            ObstacleKind::Pipe => 1.0,
+            ObstacleKind::Narrow => 0.8,
+            ObstacleKind::Wide => 1.2,
+        }
+    }
+}
+
+impl Level {
+    pub fn pickups(&self) -> u32 {
+        self.obstacles.iter().filter(|o| o.pickup).count() as u32
+    }
+
+    pub fn rate(&self, pickups: u32) -> u8 {
+        1 + u8::from(pickups >= self.stars.0) + u8::from(pickups >= self.stars.1)
+    }
+}
+
+#[derive(Default)]
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level: Level = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// Handles to every level in `LEVELS`, in the same order.
+#[derive(Resource)]
+pub struct Levels {
+    pub handles: Vec<Handle<Level>>,
+}
+
+/// Best star rating for each finished level, keyed by path.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct LevelProgress {
+    stars: BTreeMap<String, u8>,
+}
+
+impl LevelProgress {
+    pub fn load() -> Self {
+        storage::load(PROGRESS_FILE)
+    }
+
+    pub fn save(&self) {
+        storage::save(PROGRESS_FILE, self);
+    }
+
+    pub fn stars(&self, index: usize) -> Option<u8> {
+        self.stars.get(LEVELS[index]).copied()
+    }
+}
+
+/// Which level the level select screen picked, and for playtests the
+/// obstacle to start from.
+#[derive(Resource, Default)]
+pub struct SelectedLevel {
+    pub index: usize,
+    pub start: usize,
+}
+
+/// Present while a level is being playtested from the editor, which keeps
+/// it from counting towards the player's stars. Holds the editor's working
+/// copy, so the loaded level is left as it is on disk.
+#[derive(Resource)]
+pub struct Playtest(pub Handle<Level>);
+
+/// Present while playing a level.
+#[derive(Resource)]
+pub struct LevelRun {
+    pub index: usize,
+    /// What's being played, which is the playtest's copy in a playtest.
+    pub level: Handle<Level>,
+    pub pickups: u32,
+    /// Obstacle the run started from, which is only ever past the first
+    /// when playtesting from the editor.
+    start: usize,
+    elapsed: f32,
+    next: usize,
+    /// Obstacles passed when last checked.
+    passed: usize,
+    checkpoint: Option<Checkpoint>,
+}
+
+#[derive(Clone, Copy)]
+struct Checkpoint {
+    resume: usize,
+    pickups: u32,
+}
+
+impl LevelRun {
+    /// Winds back to the last checkpoint, returning the score to restart
+    /// on, or `None` if there isn't one yet.
+    pub fn rewind(&mut self, level: &Level) -> Option<u32> {
+        let checkpoint = self.checkpoint?;
+        self.next = checkpoint.resume;
+        self.passed = checkpoint.resume;
+        self.pickups = checkpoint.pickups;
+        self.elapsed = level
+            .obstacles
+            .get(checkpoint.resume)
+            .map_or(0.0, |o| o.at - RESUME_LEAD);
+        Some((checkpoint.resume - self.start) as u32)
+    }
+}
+
+#[derive(Component)]
+struct Pickup;
+
+fn load_levels(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.init_resource::<SelectedLevel>();
+    commands.insert_resource(Levels {
+        handles: LEVELS.iter().map(|path| asset_server.load(*path)).collect(),
+    });
+}
+
+fn start_run(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    selected: Res<SelectedLevel>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    playtest: Option<Res<Playtest>>,
+) {
+    commands.remove_resource::<LevelRun>();
+    if *mode != GameMode::Level {
+        return;
+    }
+
+    let level = match playtest {
+        Some(playtest) => playtest.0.clone(),
+        None => levels.handles[selected.index].clone(),
+    };
+    let start = selected.start;
+    let elapsed = match start {
+        0 => 0.0,
+        _ => level_assets
+            .get(&level)
+            .and_then(|level| level.obstacles.get(start))
+            .map_or(0.0, |o| o.at - RESUME_LEAD),
+    };
+    commands.insert_resource(LevelRun {
+        index: selected.index,
+        level,
+        pickups: 0,
+        start,
+        elapsed,
+        next: start,
+        passed: start,
+        checkpoint: None,
+    });
+}
+
+fn level_spawner(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    run: Option<ResMut<LevelRun>>,
+    level_assets: Res<Assets<Level>>,
+    pillar_shared: Res<PillarShared>,
+    time: Res<Time>,
+) {
+    let Some(mut run) = run else {
+        return;
+    };
+    let Some(level) = level_assets.get(&run.level) else {
+        return;
+    };
+
+    run.elapsed += time.delta_seconds();
+    while let Some(obstacle) = level.obstacles.get(run.next) {
+        if obstacle.at > run.elapsed {
+            break;
+        }
+        run.next += 1;
+        spawn_obstacle(&mut commands, &asset_server, &pillar_shared, obstacle);
+    }
+}
+
+/// Spawns an obstacle's pillar, with its pickup if it has one. Shared with
+/// the editor so levels look there just as they play.
+pub fn spawn_obstacle(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    pillar_shared: &PillarShared,
+    obstacle: &Obstacle,
+) -> Entity {
+    let scale = obstacle.kind.gap_scale();
+    let pillar = spawn_piller(
+        commands,
+        pillar_shared,
+        0,
+        obstacle.gap,
+        scale,
+        obstacle.kind,
+    );
+    if obstacle.pickup {
+        let y = (GAP_TOP + GAP_BOTTOM) / 2.0;
+        commands.entity(pillar).with_children(|parent| {
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::splat(28.0)),
+                        color: Color::rgb(2.0, 1.6, 0.3),
+                        ..Default::default()
+                    },
+                    // undo the pillar's scale so the pickup keeps its shape
+                    transform: Transform::from_xyz(0.0, y, 1.0).with_scale(Vec3::new(
+                        1.0 / PILLAR_SCALE,
+                        1.0 / (PILLAR_SCALE * scale),
+                        1.0,
+                    )),
+                    texture: asset_server.load("textures/babee.png"),
+                    ..Default::default()
+                },
+                Pickup,
+            ));
+        });
+    }
+    pillar
+}
+
+fn pickups(
+    mut commands: Commands,
+    run: Option<ResMut<LevelRun>>,
+    pickups: Query<(Entity, &GlobalTransform), With<Pickup>>,
+    bees: Query<&Transform, With<BeeFly>>,
+    mut sfx: EventWriter<PlaySfx>,
+    mut particles: EventWriter<SpawnParticles>,
+    mut tally: EventWriter<Tally>,
+) {
+    let Some(mut run) = run else {
+        return;
+    };
+    let Ok(bee) = bees.get_single() else {
+        return;
+    };
+
+    for (e, t) in pickups.iter() {
+        let at = t.translation().truncate();
+        if at.distance(bee.translation.truncate()) < PICKUP_RADIUS {
+            run.pickups += 1;
+            tally.send(Tally::Pollen);
+            commands.entity(e).despawn_recursive();
+            sfx.send(PlaySfx::at(SoundEffect::Flap, at.x));
+            particles.send(SpawnParticles {
+                kind: ParticleKind::Spark,
+                at,
+            });
+        }
+    }
+}
+
+fn level_progress(
+    run: Option<ResMut<LevelRun>>,
+    level_assets: Res<Assets<Level>>,
+    game_info: Res<GameInfo>,
+    playtest: Option<Res<Playtest>>,
+    mut progress: ResMut<LevelProgress>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mut run) = run else {
+        return;
+    };
+    let Some(level) = level_assets.get(&run.level) else {
+        return;
+    };
+
+    // obstacles are passed in order, so the score says which was last
+    let passed = run.start + game_info.score as usize;
+    if passed == run.passed {
+        return;
+    }
+    run.passed = passed;
+    if let Some(obstacle) = passed.checked_sub(1).and_then(|i| level.obstacles.get(i)) {
+        if obstacle.checkpoint {
+            run.checkpoint = Some(Checkpoint {
+                resume: passed,
+                pickups: run.pickups,
+            });
+        }
+    }
+
+    if passed >= level.obstacles.len() {
+        let stars = level.rate(run.pickups);
+        let best = progress
+            .stars
+            .entry(LEVELS[run.index].to_string())
+            .or_default();
+        if stars > *best && playtest.is_none() {
+            *best = stars;
+            progress.save();
+        }
+        game_state.set(GameState::Menu);
+    }
+}
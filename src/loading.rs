@@ -0,0 +1,217 @@
+use std::path::Path;
+
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{
+    animation, audio::MusicTrack, biomes::Biome, levels::LEVELS, parallax, settings::Settings,
+    sfx::SoundEffect, skins::Skin, view, GameState,
+};
+
+/// Textures and fonts used anywhere in the game. Sounds and levels are
+/// collected from their own tables so they can't drift out of date.
+const MANIFEST: &[&str] = &[
+    "fonts/goodtimes.otf",
+    "fonts/HoneyBee-Regular.ttf",
+    "textures/babee.png",
+    "textures/bg2.png",
+    "textures/cloud1.png",
+    "textures/logo.png",
+    "textures/propeller.png",
+    "textures/scoreboard.png",
+];
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(loading_setup.in_schedule(OnEnter(GameState::Loading)))
+            .add_system(loading_progress.in_set(OnUpdate(GameState::Loading)))
+            .add_system(cleanup.in_schedule(OnExit(GameState::Loading)));
+    }
+}
+
+/// Keeps every asset in the manifest loaded for the life of the game, so
+/// later `asset_server.load` calls hit the cache instead of popping in.
+#[derive(Resource, Default)]
+pub struct Preloaded {
+    handles: Vec<(String, HandleUntyped)>,
+    missing: Vec<String>,
+    done: bool,
+}
+
+#[derive(Component)]
+struct LoadingMarker;
+
+#[derive(Component)]
+struct ProgressBar;
+
+#[derive(Component)]
+struct ProgressText;
+
+fn manifest() -> Vec<&'static str> {
+    let mut paths = MANIFEST.to_vec();
+    for track in MusicTrack::ALL {
+        paths.extend(track.stems().iter().map(|stem| stem.path));
+    }
+    for effect in SoundEffect::ALL {
+        paths.extend(effect.variants());
+    }
+    paths.extend(LEVELS);
+    paths.push(parallax::LAYERS);
+    paths.push(animation::CLIPS);
+    for skin in Skin::ALL {
+        paths.extend(skin.textures());
+    }
+    for biome in Biome::ALL {
+        paths.push(biome.pillars().texture);
+    }
+
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn loading_setup(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+    let mut preloaded = Preloaded::default();
+    for path in manifest() {
+        if asset_server.asset_io().is_file(Path::new(path)) {
+            preloaded
+                .handles
+                .push((path.to_string(), asset_server.load_untyped(path)));
+        } else {
+            error!("missing asset: {}", path);
+            preloaded.missing.push(path.to_string());
+        }
+    }
+    commands.insert_resource(preloaded);
+
+    commands.spawn((
+        Camera2dBundle {
+            projection: view::projection(),
+            ..default()
+        },
+        LoadingMarker,
+    ));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: settings.palette.menu_background().into(),
+                ..default()
+            },
+            LoadingMarker,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(400.0), Val::Px(24.0)),
+                        padding: UiRect::all(Val::Px(3.0)),
+                        ..default()
+                    },
+                    background_color: settings.palette.button_text().into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: settings.palette.button_pressed().into(),
+                            ..default()
+                        },
+                        ProgressBar,
+                    ));
+                });
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/goodtimes.otf"),
+                        font_size: 20.0,
+                        color: settings.palette.button_text(),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..default()
+                }),
+                ProgressText,
+            ));
+        });
+}
+
+fn loading_progress(
+    mut preloaded: ResMut<Preloaded>,
+    asset_server: Res<AssetServer>,
+    keys: Res<Input<KeyCode>>,
+    mut bars: Query<&mut Style, With<ProgressBar>>,
+    mut texts: Query<&mut Text, With<ProgressText>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if preloaded.done {
+        if keys.get_just_pressed().next().is_some() {
+            game_state.set(GameState::Menu);
+        }
+        return;
+    }
+
+    let mut finished = 0;
+    let mut failed = Vec::new();
+    for (path, handle) in &preloaded.handles {
+        match asset_server.get_load_state(handle) {
+            LoadState::Loaded => finished += 1,
+            LoadState::Failed => {
+                finished += 1;
+                failed.push(path.clone());
+            }
+            _ => (),
+        }
+    }
+
+    let total = preloaded.handles.len().max(1);
+    for mut style in bars.iter_mut() {
+        style.size.width = Val::Percent(100.0 * finished as f32 / total as f32);
+    }
+
+    if finished < preloaded.handles.len() {
+        return;
+    }
+
+    for path in &failed {
+        error!("failed to load asset: {}", path);
+    }
+
+    if preloaded.missing.is_empty() && failed.is_empty() {
+        game_state.set(GameState::Menu);
+        return;
+    }
+
+    // stay on the loading screen so broken installs are obvious
+    let mut report = String::new();
+    for path in &preloaded.missing {
+        report += &format!("missing: {}\n", path);
+    }
+    for path in &failed {
+        report += &format!("failed: {}\n", path);
+    }
+    report += "\npress any key to continue";
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = report.clone();
+    }
+    preloaded.done = true;
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<LoadingMarker>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
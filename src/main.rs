@@ -3,17 +3,47 @@ use bevy::prelude::*;
 use bevy_kira_audio::AudioPlugin;
 use bevy_prototype_debug_lines::*;
 
+mod animation;
+mod audio;
 mod bee_game;
+mod biomes;
+mod bosses;
+mod camera;
+mod daily;
+mod editor;
+mod environment;
+mod ghost;
+mod leaderboard;
+mod levels;
+mod loading;
 mod menu;
+mod modes;
+mod net;
+mod parallax;
+mod particles;
+mod scores;
+mod settings;
+mod sfx;
+mod sim;
+mod skins;
+mod stats;
+mod storage;
+mod view;
 
 #[derive(States, Default, Debug, Hash, Eq, PartialEq, Clone)]
 enum GameState {
     #[default]
+    Loading,
     Menu,
     Game,
+    Editor,
+    /// Waiting with other players for an online race to start.
+    Lobby,
 }
 
 fn main() {
+    let settings = settings::Settings::load();
+
     App::new()
         .add_state::<GameState>()
         .add_plugins(
@@ -22,7 +52,9 @@ fn main() {
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         title: "Beescape".into(),
-                        resizable: false,
+                        resizable: true,
+                        mode: settings.window_mode(),
+                        present_mode: settings.present_mode(),
                         ..Default::default()
                     }),
                     ..Default::default()
@@ -30,7 +62,29 @@ fn main() {
         )
         .add_plugin(DebugLinesPlugin::default())
         .add_plugin(AudioPlugin)
+        .insert_resource(settings)
+        .add_plugin(settings::SettingsPlugin)
+        .add_plugin(view::ViewPlugin)
+        .add_plugin(audio::GameAudioPlugin)
+        .add_plugin(sfx::SfxPlugin)
+        .add_plugin(loading::LoadingPlugin)
+        .add_plugin(animation::AnimationPlugin)
+        .add_plugin(modes::ModesPlugin)
+        .add_plugin(daily::DailyPlugin)
+        .add_plugin(ghost::GhostPlugin)
+        .add_plugin(stats::StatsPlugin)
+        .add_plugin(skins::SkinsPlugin)
+        .add_plugin(leaderboard::LeaderboardPlugin)
+        .add_plugin(net::NetPlugin)
+        .add_plugin(levels::LevelsPlugin)
+        .add_plugin(editor::EditorPlugin)
         .add_plugin(bee_game::BeeGame)
+        .add_plugin(biomes::BiomesPlugin)
+        .add_plugin(bosses::BossesPlugin)
+        .add_plugin(parallax::ParallaxPlugin)
+        .add_plugin(particles::ParticlePlugin)
+        .add_plugin(environment::EnvironmentPlugin)
+        .add_plugin(camera::CameraPlugin)
         .add_plugin(menu::MenuPlugin)
         .run();
 }
@@ -1,7 +1,16 @@
 use std::time::Duration;
 
-use super::bee_game::{AnimInfo, BeeFly, GameInfo};
-use crate::GameState;
+use super::bee_game::{score_text_bundle, BeeFly, GameInfo};
+use crate::{
+    animation::{Animator, Clip},
+    daily::DailyHistory,
+    levels::{Level, LevelProgress, Levels, SelectedLevel},
+    modes::GameMode,
+    settings::{step_volume, Settings},
+    skins::{Dressed, Skin, Unlock, Wardrobe},
+    stats::{Achievement, Stats},
+    view, GameState,
+};
 use bevy::prelude::*;
 
 pub struct MenuPlugin;
@@ -9,7 +18,17 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(menu_setup.in_schedule(OnEnter(GameState::Menu)))
+            .add_system(
+                screen_setup
+                    .run_if(resource_changed::<MenuScreen>())
+                    .in_set(OnUpdate(GameState::Menu)),
+            )
             .add_system(button_system.in_set(OnUpdate(GameState::Menu)))
+            .add_system(
+                repaint
+                    .run_if(resource_changed::<Settings>())
+                    .in_set(OnUpdate(GameState::Menu)),
+            )
             .add_system(cleanup.in_schedule(OnExit(GameState::Menu)));
     }
 }
@@ -17,19 +36,58 @@ impl Plugin for MenuPlugin {
 #[derive(Component)]
 struct MenuMarker;
 
+/// Marks the buttons belonging to the current `MenuScreen`, which get rebuilt
+/// whenever the screen changes.
+#[derive(Component)]
+struct ScreenMarker;
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq)]
+enum MenuScreen {
+    Main,
+    Options,
+    Levels,
+    Achievements,
+    Skins,
+}
+
 #[derive(Component)]
 enum ButtonIdent {
     Play,
+    Mode,
+    Levels,
+    Level(usize),
+    Editor,
+    Options,
     Exit,
+    Back,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Fullscreen,
+    Vsync,
+    ScreenShake,
+    Ghost,
+    Controls,
+    Palette,
+    Language,
+    Achievements,
+    Skins,
+    Skin(Skin),
 }
 
 fn menu_setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     game_info: Option<Res<GameInfo>>,
+    settings: Res<Settings>,
+    mode: Res<GameMode>,
 ) {
-    let _ = asset_server.load::<Image, &str>("textures/bg2.png");
+    // coming back from a level goes straight back to picking the next one
+    commands.insert_resource(match *mode {
+        GameMode::Level => MenuScreen::Levels,
+        _ => MenuScreen::Main,
+    });
+
     commands.spawn((
         Camera2dBundle {
             camera: Camera {
@@ -38,106 +96,15 @@ fn menu_setup(
             },
             camera_2d: Camera2d {
                 clear_color: bevy::core_pipeline::clear_color::ClearColorConfig::Custom(
-                    Color::rgb(1.0, 0.95, 0.84),
+                    settings.palette.menu_background(),
                 ),
             },
+            projection: view::projection(),
             ..default()
         },
         MenuMarker,
     ));
-    commands
-        .spawn((
-            NodeBundle {
-                style: Style {
-                    size: Size::width(Val::Percent(100.0)),
-                    align_items: AlignItems::Center,
-                    justify_content: JustifyContent::Center,
-                    margin: UiRect {
-                        bottom: Val::Percent(-30.0),
-                        ..Default::default()
-                    },
-                    ..default()
-                },
-                ..default()
-            },
-            MenuMarker,
-        ))
-        .with_children(|parent| {
-            parent
-                .spawn((
-                    ButtonBundle {
-                        style: Style {
-                            size: Size::new(Val::Px(200.0), Val::Px(75.0)),
-                            // horizontally center child text
-                            justify_content: JustifyContent::Center,
-                            // vertically center child text
-                            align_items: AlignItems::Center,
-                            margin: UiRect {
-                                left: Val::Percent(5.),
-                                right: Val::Percent(5.),
-                                top: Val::Percent(15.),
-                                bottom: Val::Percent(5.),
-                            },
-                            ..default()
-                        },
-                        background_color: NORMAL_BUTTON.into(),
-                        ..default()
-                    },
-                    ButtonIdent::Play,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Play",
-                        TextStyle {
-                            font: asset_server.load("fonts/goodtimes.otf"),
-                            font_size: 40.0,
-                            color: Color::rgb(0.0, 0.08, 0.20),
-                        },
-                    ));
-                });
-            parent
-                .spawn((
-                    ButtonBundle {
-                        style: Style {
-                            size: Size::new(Val::Px(200.0), Val::Px(75.0)),
-                            // horizontally center child text
-                            justify_content: JustifyContent::Center,
-                            // vertically center child text
-                            align_items: AlignItems::Center,
-                            margin: UiRect {
-                                left: Val::Percent(5.),
-                                right: Val::Percent(5.),
-                                top: Val::Percent(15.),
-                                bottom: Val::Percent(5.),
-                            },
-                            ..default()
-                        },
-                        background_color: NORMAL_BUTTON.into(),
-                        ..default()
-                    },
-                    ButtonIdent::Exit,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Exit",
-                        TextStyle {
-                            font: asset_server.load("fonts/goodtimes.otf"),
-                            font_size: 40.0,
-                            color: Color::rgb(0.0, 0.08, 0.20),
-                        },
-                    ));
-                });
-        });
-
     let center = Vec2::new(0.0, 90.0);
-    let texture_atlas = texture_atlases.add(TextureAtlas::from_grid(
-        asset_server.load("textures/bee.png"),
-        Vec2::new(32.0, 32.0),
-        2,
-        1,
-        Some(Vec2::new(1.0, 0.0)),
-        None,
-    ));
 
     commands.spawn((
         SpriteBundle {
@@ -155,9 +122,9 @@ fn menu_setup(
                 ..Default::default()
             },
             transform: Transform::from_xyz(center.x, center.y - 300.0, 500.0),
-            texture_atlas: texture_atlas.clone(),
             ..Default::default()
         },
+        Dressed,
         BeeFly {
             aim: center,
             center,
@@ -165,10 +132,7 @@ fn menu_setup(
             height: 500.0,
             timer: Timer::new(Duration::from_millis(2000), TimerMode::Repeating),
         },
-        AnimInfo {
-            timer: Timer::new(Duration::from_millis(500), TimerMode::Repeating),
-            num: 2,
-        },
+        Animator::new(Clip::Idle),
         MenuMarker,
     ));
 
@@ -179,9 +143,9 @@ fn menu_setup(
                 ..Default::default()
             },
             transform: Transform::from_xyz(center.x - 500.0, center.y - 350.0, 500.0),
-            texture_atlas: texture_atlas.clone(),
             ..Default::default()
         },
+        Dressed,
         BeeFly {
             aim: center,
             center,
@@ -189,10 +153,7 @@ fn menu_setup(
             height: 500.0,
             timer: Timer::new(Duration::from_millis(2000), TimerMode::Repeating),
         },
-        AnimInfo {
-            timer: Timer::new(Duration::from_millis(500), TimerMode::Repeating),
-            num: 2,
-        },
+        Animator::new(Clip::Idle),
         MenuMarker,
     ));
 
@@ -203,9 +164,9 @@ fn menu_setup(
                 ..Default::default()
             },
             transform: Transform::from_xyz(center.x + 500.0, center.y - 350.0, 500.0),
-            texture_atlas: texture_atlas.clone(),
             ..Default::default()
         },
+        Dressed,
         BeeFly {
             aim: center,
             center,
@@ -213,10 +174,7 @@ fn menu_setup(
             height: 500.0,
             timer: Timer::new(Duration::from_millis(2000), TimerMode::Repeating),
         },
-        AnimInfo {
-            timer: Timer::new(Duration::from_millis(500), TimerMode::Repeating),
-            num: 2,
-        },
+        Animator::new(Clip::Idle),
         MenuMarker,
     ));
 
@@ -224,23 +182,7 @@ fn menu_setup(
         let score = format!("{}", game_info.score);
 
         commands.spawn((
-            TextBundle::from_section(
-                score,
-                TextStyle {
-                    font: asset_server.load("fonts/HoneyBee-Regular.ttf"),
-                    font_size: 120.0,
-                    color: Color::rgb(0.9, 0.8, 0.5),
-                },
-            )
-            .with_style(Style {
-                position_type: PositionType::Absolute,
-                position: UiRect {
-                    top: Val::Percent(9.0),
-                    left: Val::Percent(07.8),
-                    ..Default::default()
-                },
-                ..Default::default()
-            }),
+            score_text_bundle(score, &asset_server, &settings),
             MenuMarker,
         ));
         commands.spawn((
@@ -254,10 +196,557 @@ fn menu_setup(
     }
 }
 
-const NORMAL_BUTTON: Color = Color::rgb(1.0, 0.92, 0.80);
-const HOVERED_BUTTON: Color = Color::rgb(1.0, 0.94, 0.57);
-const PRESSED_BUTTON: Color = Color::rgb(1.0, 0.84, 0.48);
+#[allow(clippy::too_many_arguments)]
+fn screen_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    screen: Res<MenuScreen>,
+    settings: Res<Settings>,
+    mode: Res<GameMode>,
+    history: Res<DailyHistory>,
+    levels: Res<Levels>,
+    level_assets: Res<Assets<Level>>,
+    progress: Res<LevelProgress>,
+    stats: Res<Stats>,
+    wardrobe: Res<Wardrobe>,
+    old: Query<Entity, With<ScreenMarker>>,
+) {
+    for e in old.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+
+    match *screen {
+        MenuScreen::Main => {
+            main_screen(&mut commands, &asset_server, &settings, *mode);
+            if *mode == GameMode::Daily {
+                daily_history(&mut commands, &asset_server, &settings, &history);
+            }
+        }
+        MenuScreen::Options => options_screen(&mut commands, &asset_server, &settings),
+        MenuScreen::Achievements => {
+            achievements_screen(&mut commands, &asset_server, &settings, &stats)
+        }
+        MenuScreen::Skins => {
+            skins_screen(&mut commands, &asset_server, &settings, &stats, &wardrobe)
+        }
+        MenuScreen::Levels => {
+            levels_screen(
+                &mut commands,
+                &asset_server,
+                &settings,
+                &levels,
+                &level_assets,
+                &progress,
+            );
+        }
+    }
+}
+
+fn main_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    settings: &Settings,
+    mode: GameMode,
+) {
+    let lang = settings.language;
+    let margin = UiRect {
+        left: Val::Percent(1.),
+        right: Val::Percent(1.),
+        top: Val::Percent(15.),
+        bottom: Val::Percent(5.),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::width(Val::Percent(100.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    margin: UiRect {
+                        bottom: Val::Percent(-30.0),
+                        ..Default::default()
+                    },
+                    ..default()
+                },
+                ..default()
+            },
+            MenuMarker,
+            ScreenMarker,
+        ))
+        .with_children(|parent| {
+            for (label, ident) in [
+                ("Play", ButtonIdent::Play),
+                ("Levels", ButtonIdent::Levels),
+                ("Options", ButtonIdent::Options),
+                ("Exit", ButtonIdent::Exit),
+            ] {
+                spawn_button(
+                    parent,
+                    asset_server,
+                    settings,
+                    lang.tr(label).to_string(),
+                    ident,
+                    Style {
+                        size: Size::new(Val::Px(200.0), Val::Px(75.0)),
+                        margin,
+                        ..default()
+                    },
+                    40.0,
+                );
+            }
+
+            // wider, to fit the longer mode names
+            spawn_button(
+                parent,
+                asset_server,
+                settings,
+                lang.tr(mode.name()).to_string(),
+                ButtonIdent::Mode,
+                Style {
+                    size: Size::new(Val::Px(300.0), Val::Px(75.0)),
+                    margin,
+                    ..default()
+                },
+                28.0,
+            );
+        });
+
+    // smaller, along the bottom
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(20.0),
+                        left: Val::Px(0.0),
+                        ..default()
+                    },
+                    size: Size::width(Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            MenuMarker,
+            ScreenMarker,
+        ))
+        .with_children(|parent| {
+            for (label, ident) in [
+                ("Achievements", ButtonIdent::Achievements),
+                ("Skins", ButtonIdent::Skins),
+            ] {
+                spawn_button(
+                    parent,
+                    asset_server,
+                    settings,
+                    lang.tr(label).to_string(),
+                    ident,
+                    Style {
+                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                        margin: UiRect::horizontal(Val::Px(10.0)),
+                        ..default()
+                    },
+                    24.0,
+                );
+            }
+        });
+}
+
+/// Lists the last few daily results down the right hand side.
+fn daily_history(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    settings: &Settings,
+    history: &DailyHistory,
+) {
+    let mut text = settings.language.tr("Dailies").to_string();
+    for (date, score) in history.recent() {
+        text += &format!("\n{}  {}", date, score);
+    }
+
+    commands.spawn((
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: asset_server.load("fonts/goodtimes.otf"),
+                font_size: 22.0,
+                color: settings.palette.button_text(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(20.0),
+                right: Val::Px(20.0),
+                ..default()
+            },
+            ..default()
+        }),
+        MenuMarker,
+        ScreenMarker,
+    ));
+}
+
+fn levels_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    settings: &Settings,
+    levels: &Levels,
+    level_assets: &Assets<Level>,
+    progress: &LevelProgress,
+) {
+    let lang = settings.language;
+
+    let mut rows = Vec::new();
+    for (i, handle) in levels.handles.iter().enumerate() {
+        // failed loads are already reported on the loading screen
+        let Some(level) = level_assets.get(handle) else {
+            continue;
+        };
+        let stars = match progress.stars(i) {
+            Some(stars) => format!("{}/3", stars),
+            None => "-".to_string(),
+        };
+        rows.push((format!("{}  {}", level.name, stars), ButtonIdent::Level(i)));
+    }
+    rows.push((lang.tr("Editor").to_string(), ButtonIdent::Editor));
+    rows.push((lang.tr("Back").to_string(), ButtonIdent::Back));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: settings.palette.menu_background().with_a(0.85).into(),
+                ..default()
+            },
+            MenuMarker,
+            ScreenMarker,
+        ))
+        .with_children(|parent| {
+            for (label, ident) in rows {
+                spawn_button(
+                    parent,
+                    asset_server,
+                    settings,
+                    label,
+                    ident,
+                    Style {
+                        size: Size::new(Val::Px(480.0), Val::Px(60.0)),
+                        margin: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    28.0,
+                );
+            }
+        });
+}
+
+fn options_screen(commands: &mut Commands, asset_server: &Res<AssetServer>, settings: &Settings) {
+    let lang = settings.language;
+    let percent = |v: f32| format!("{}%", (v * 100.0).round());
+
+    let rows = [
+        (
+            format!("{}: {}", lang.tr("Master"), percent(settings.master_volume)),
+            ButtonIdent::MasterVolume,
+        ),
+        (
+            format!("{}: {}", lang.tr("Music"), percent(settings.music_volume)),
+            ButtonIdent::MusicVolume,
+        ),
+        (
+            format!("{}: {}", lang.tr("Effects"), percent(settings.sfx_volume)),
+            ButtonIdent::SfxVolume,
+        ),
+        (
+            format!(
+                "{}: {}",
+                lang.tr("Display"),
+                match settings.fullscreen {
+                    true => lang.tr("Fullscreen"),
+                    false => lang.tr("Windowed"),
+                }
+            ),
+            ButtonIdent::Fullscreen,
+        ),
+        (
+            format!("{}: {}", lang.tr("Vsync"), lang.on_off(settings.vsync)),
+            ButtonIdent::Vsync,
+        ),
+        (
+            format!(
+                "{}: {}",
+                lang.tr("Screen shake"),
+                lang.on_off(settings.screen_shake)
+            ),
+            ButtonIdent::ScreenShake,
+        ),
+        (
+            format!("{}: {}", lang.tr("Race ghost"), lang.on_off(settings.ghost)),
+            ButtonIdent::Ghost,
+        ),
+        (
+            format!(
+                "{}: {}",
+                lang.tr("Controls"),
+                lang.tr(settings.controls.name())
+            ),
+            ButtonIdent::Controls,
+        ),
+        (
+            format!("{}: {}", lang.tr("Palette"), settings.palette.name()),
+            ButtonIdent::Palette,
+        ),
+        (
+            format!("{}: {}", lang.tr("Language"), lang.name()),
+            ButtonIdent::Language,
+        ),
+        (lang.tr("Back").to_string(), ButtonIdent::Back),
+    ];
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: settings.palette.menu_background().with_a(0.85).into(),
+                ..default()
+            },
+            MenuMarker,
+            ScreenMarker,
+        ))
+        .with_children(|parent| {
+            for (label, ident) in rows {
+                spawn_button(
+                    parent,
+                    asset_server,
+                    settings,
+                    label,
+                    ident,
+                    Style {
+                        size: Size::new(Val::Px(480.0), Val::Px(52.0)),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                    26.0,
+                );
+            }
+        });
+}
+
+/// Lifetime stats on the left, achievements on the right, greyed out until
+/// unlocked.
+fn achievements_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    settings: &Settings,
+    stats: &Stats,
+) {
+    let lang = settings.language;
+    let font = asset_server.load("fonts/goodtimes.otf");
+    let text = settings.palette.button_text();
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: settings.palette.menu_background().with_a(0.85).into(),
+                ..default()
+            },
+            MenuMarker,
+            ScreenMarker,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            stats.summary(lang),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 24.0,
+                                color: text,
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::right(Val::Px(60.0)),
+                            ..default()
+                        }),
+                    );
+
+                    let sections = Achievement::ALL.into_iter().map(|a| {
+                        let alpha = if stats.unlocked(a) { 1.0 } else { 0.35 };
+                        TextSection::new(
+                            format!("{}  -  {}\n", lang.tr(a.name()), lang.tr(a.description())),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 22.0,
+                                color: text.with_a(alpha),
+                            },
+                        )
+                    });
+                    parent.spawn(TextBundle::from_sections(sections));
+                });
+
+            spawn_button(
+                parent,
+                asset_server,
+                settings,
+                lang.tr("Back").to_string(),
+                ButtonIdent::Back,
+                Style {
+                    size: Size::new(Val::Px(480.0), Val::Px(60.0)),
+                    margin: UiRect::all(Val::Px(6.0)),
+                    ..default()
+                },
+                28.0,
+            );
+        });
+}
+
+/// A button per skin saying what it takes to wear. The background is kept
+/// light so the menu's bees show off the one picked.
+fn skins_screen(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    settings: &Settings,
+    stats: &Stats,
+    wardrobe: &Wardrobe,
+) {
+    let lang = settings.language;
+
+    let mut rows = Vec::new();
+    for skin in Skin::ALL {
+        let status = if skin == wardrobe.worn() {
+            lang.tr("Worn").to_string()
+        } else if wardrobe.owns(skin, stats) {
+            String::new()
+        } else {
+            match skin.unlock() {
+                Unlock::Free => String::new(),
+                Unlock::Achievement(a) => lang.tr(a.name()).to_string(),
+                Unlock::Pollen(price) => format!("{} {}", price, lang.tr("Pollen")),
+            }
+        };
+        rows.push((
+            format!("{}  {}", lang.tr(skin.name()), status),
+            ButtonIdent::Skin(skin),
+        ));
+    }
+    rows.push((lang.tr("Back").to_string(), ButtonIdent::Back));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: settings.palette.menu_background().with_a(0.5).into(),
+                ..default()
+            },
+            MenuMarker,
+            ScreenMarker,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    format!("{}: {}", lang.tr("Pollen"), wardrobe.pollen(stats)),
+                    TextStyle {
+                        font: asset_server.load("fonts/goodtimes.otf"),
+                        font_size: 28.0,
+                        color: settings.palette.button_text(),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(12.0)),
+                    ..default()
+                }),
+            );
+            for (label, ident) in rows {
+                spawn_button(
+                    parent,
+                    asset_server,
+                    settings,
+                    label,
+                    ident,
+                    Style {
+                        size: Size::new(Val::Px(560.0), Val::Px(60.0)),
+                        margin: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    26.0,
+                );
+            }
+        });
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    settings: &Settings,
+    label: String,
+    ident: ButtonIdent,
+    style: Style,
+    font_size: f32,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    // horizontally center child text
+                    justify_content: JustifyContent::Center,
+                    // vertically center child text
+                    align_items: AlignItems::Center,
+                    ..style
+                },
+                background_color: settings.palette.button_normal().into(),
+                ..default()
+            },
+            ident,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font: asset_server.load("fonts/goodtimes.otf"),
+                    font_size,
+                    color: settings.palette.button_text(),
+                },
+            ));
+        });
+}
 
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor, &Children, &ButtonIdent),
@@ -266,31 +755,88 @@ fn button_system(
     mut text_query: Query<&mut Text>,
     mut game_state: ResMut<NextState<GameState>>,
     mut exit: EventWriter<bevy::app::AppExit>,
+    mut screen: ResMut<MenuScreen>,
+    mut settings: ResMut<Settings>,
+    mut mode: ResMut<GameMode>,
+    mut selected: ResMut<SelectedLevel>,
+    mut wardrobe: ResMut<Wardrobe>,
+    stats: Res<Stats>,
 ) {
+    let palette = settings.palette;
+
     for (interaction, mut color, children, button) in &mut interaction_query {
         let _text = text_query.get_mut(children[0]).unwrap();
         match *interaction {
             Interaction::Clicked => {
                 //text.sections[0].value = "Bzzzz".to_string();
-                *color = PRESSED_BUTTON.into();
+                *color = palette.button_pressed().into();
                 use ButtonIdent::*;
                 match button {
-                    Play => game_state.set(GameState::Game),
+                    Play => game_state.set(match *mode {
+                        GameMode::Online => GameState::Lobby,
+                        _ => GameState::Game,
+                    }),
+                    Mode => *mode = mode.next(),
+                    Levels => *screen = MenuScreen::Levels,
+                    Level(i) => {
+                        *selected = SelectedLevel {
+                            index: *i,
+                            start: 0,
+                        };
+                        *mode = GameMode::Level;
+                        game_state.set(GameState::Game);
+                    }
+                    // opens on the last level picked, tab moves between them
+                    Editor => game_state.set(GameState::Editor),
+                    Options => *screen = MenuScreen::Options,
                     Exit => exit.send(bevy::app::AppExit),
+                    Back => {
+                        if *mode == GameMode::Level {
+                            *mode = GameMode::Endless;
+                        }
+                        *screen = MenuScreen::Main;
+                    }
+                    MasterVolume => settings.master_volume = step_volume(settings.master_volume),
+                    MusicVolume => settings.music_volume = step_volume(settings.music_volume),
+                    SfxVolume => settings.sfx_volume = step_volume(settings.sfx_volume),
+                    Fullscreen => settings.fullscreen = !settings.fullscreen,
+                    Vsync => settings.vsync = !settings.vsync,
+                    ScreenShake => settings.screen_shake = !settings.screen_shake,
+                    Ghost => settings.ghost = !settings.ghost,
+                    Controls => settings.controls = settings.controls.next(),
+                    Palette => settings.palette = settings.palette.next(),
+                    Language => settings.language = settings.language.next(),
+                    Achievements => *screen = MenuScreen::Achievements,
+                    Skins => *screen = MenuScreen::Skins,
+                    Skin(skin) => wardrobe.pick(*skin, &stats),
+                }
+
+                // option labels show the current value, so redraw the screen
+                if settings.is_changed() || mode.is_changed() || wardrobe.is_changed() {
+                    screen.set_changed();
                 }
             }
             Interaction::Hovered => {
                 //text.sections[0].value = "Bzzzz".to_string();
-                *color = HOVERED_BUTTON.into();
+                *color = palette.button_hovered().into();
             }
             Interaction::None => {
                 //text.sections[0].value = "Play".to_string();
-                *color = NORMAL_BUTTON.into();
+                *color = palette.button_normal().into();
             }
         }
     }
 }
 
+/// Screens are rebuilt in the new palette, but the camera behind them stays.
+fn repaint(settings: Res<Settings>, mut cameras: Query<&mut Camera2d, With<MenuMarker>>) {
+    for mut camera in cameras.iter_mut() {
+        camera.clear_color = bevy::core_pipeline::clear_color::ClearColorConfig::Custom(
+            settings.palette.menu_background(),
+        );
+    }
+}
+
 fn cleanup(mut commands: Commands, query: Query<Entity, With<MenuMarker>>) {
     for e in query.iter() {
         commands.entity(e).despawn_recursive();
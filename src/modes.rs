@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bee_game::{GameInfo, Player},
+    daily::DailyRun,
+    levels::{Level, LevelRun},
+    net::Session,
+    scores::HighScores,
+    settings::Settings,
+    sim::{self, Rules},
+    GameState,
+};
+
+pub struct ModesPlugin;
+
+impl Plugin for ModesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .add_system(hud_setup.in_schedule(OnEnter(GameState::Game)))
+            .add_system(hud_update.in_set(OnUpdate(GameState::Game)))
+            .add_system(cleanup.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+/// Picked on the menu and fixed for the length of a run.
+#[derive(
+    Resource,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+)]
+pub enum GameMode {
+    #[default]
+    Endless,
+    TimeAttack,
+    Zen,
+    SuddenDeath,
+    /// Same layout for everyone on a given day, see `daily`.
+    Daily,
+    /// Two bees side by side through the same pillars, last one flying wins.
+    Versus,
+    /// Racing other players over the network, see `net`.
+    Online,
+    /// An authored level from `levels`, picked on the level select screen
+    /// rather than cycled to.
+    Level,
+}
+
+impl GameMode {
+    pub fn next(self) -> Self {
+        use GameMode::*;
+        match self {
+            Endless => TimeAttack,
+            TimeAttack => Zen,
+            Zen => SuddenDeath,
+            SuddenDeath => Daily,
+            Daily => Versus,
+            Versus => Online,
+            Online | Level => Endless,
+        }
+    }
+
+    /// English name, to be passed through `Language::tr`.
+    pub fn name(self) -> &'static str {
+        use GameMode::*;
+        match self {
+            Endless => "Endless",
+            TimeAttack => "Time Attack",
+            Zen => "Zen",
+            SuddenDeath => "Sudden Death",
+            Daily => "Daily",
+            Versus => "Versus",
+            Online => "Online",
+            Level => "Level",
+        }
+    }
+
+    /// Whether touching a pillar ends the run, rather than bouncing off it.
+    pub fn can_die(self) -> bool {
+        self != GameMode::Zen
+    }
+
+    /// Bees in the run, each with their own half of the screen.
+    pub fn players(self) -> usize {
+        match self {
+            GameMode::Versus => 2,
+            _ => 1,
+        }
+    }
+
+    /// Speed, gap and time limit, which live in `sim` so the leaderboard
+    /// server plays by the same ones.
+    pub fn rules(self) -> Rules {
+        match self {
+            GameMode::TimeAttack => sim::TIME_ATTACK,
+            GameMode::SuddenDeath => sim::SUDDEN_DEATH,
+            GameMode::Daily => sim::DAILY,
+            _ => sim::STANDARD,
+        }
+    }
+
+    /// Name runs are submitted to the leaderboard under, for modes that
+    /// have one.
+    pub fn ranked(self) -> Option<String> {
+        let name = format!("{:?}", self);
+        sim::ranked(&name).map(|_| name)
+    }
+}
+
+#[derive(Component)]
+struct ModeHud;
+
+fn hud_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+) {
+    if *mode == GameMode::Endless {
+        return;
+    }
+
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/goodtimes.otf"),
+                    font_size: 32.0,
+                    color: settings.palette.score_text(),
+                },
+            ),
+            transform: Transform::from_xyz(0.0, 320.0, 130.0),
+            ..Default::default()
+        },
+        ModeHud,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn hud_update(
+    mut query: Query<&mut Text, With<ModeHud>>,
+    mode: Res<GameMode>,
+    game_info: Res<GameInfo>,
+    high_scores: Res<HighScores>,
+    daily: Option<Res<DailyRun>>,
+    level_run: Option<Res<LevelRun>>,
+    level_assets: Res<Assets<Level>>,
+    players: Query<&Player>,
+    session: Option<Res<Session>>,
+    settings: Res<Settings>,
+) {
+    let lang = settings.language;
+    let value = match *mode {
+        GameMode::Endless => return,
+        GameMode::TimeAttack => {
+            let left = game_info
+                .clock
+                .as_ref()
+                .map_or(0.0, |clock| clock.remaining_secs());
+            format!("{:.1}", left)
+        }
+        GameMode::Zen => format!("{}: {}", lang.tr("Bounces"), game_info.bounces),
+        GameMode::SuddenDeath => {
+            format!("{}: {}", lang.tr("Best"), high_scores.best(*mode))
+        }
+        GameMode::Daily => match daily {
+            Some(run) if run.counts => run.date.clone(),
+            Some(run) => format!("{} ({})", run.date, lang.tr("Practice")),
+            None => return,
+        },
+        GameMode::Versus => {
+            let mut alive = players.iter().filter(|p| !p.is_dead());
+            match (alive.next(), alive.next()) {
+                (Some(_), Some(_)) => String::new(),
+                (Some(winner), None) => match winner.index {
+                    0 => lang.tr("Player 1 wins").to_string(),
+                    _ => lang.tr("Player 2 wins").to_string(),
+                },
+                (None, _) => lang.tr("Draw").to_string(),
+            }
+        }
+        GameMode::Online => match session {
+            Some(session) => session.standings(game_info.score, !game_info.is_dead(), lang),
+            None => return,
+        },
+        GameMode::Level => {
+            let Some(run) = level_run else {
+                return;
+            };
+            let Some(level) = level_assets.get(&run.level) else {
+                return;
+            };
+            format!("{}  {}/{}", level.name, run.pickups, level.pickups())
+        }
+    };
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<ModeHud>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -0,0 +1,714 @@
+//! Online races, where several games fly the same pillars at once and see
+//! each other as ghost bees. The first game to open the lobby at
+//! `Settings::lobby` hosts it, passing every bee on to everyone else, and
+//! later ones join it. Two games on one machine are enough to try it out.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::{Animator, Clip},
+    bee_game::{GameInfo, Player},
+    ghost::GHOST_ALPHA,
+    settings::{Language, Settings},
+    sim::Sample,
+    view, GameState,
+};
+
+/// Seconds from everyone being ready to the race starting.
+const COUNTDOWN: f32 = 3.0;
+/// Seconds between packets to each player.
+const SEND_EVERY: f32 = 0.05;
+/// Seconds of silence before a player is taken to have gone.
+const TIMEOUT: f64 = 5.0;
+const MAX_PLAYERS: usize = 8;
+const MAX_PACKET: usize = 2048;
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(lobby_setup.in_schedule(OnEnter(GameState::Lobby)))
+            .add_systems(
+                (lobby_buttons, lobby_update)
+                    .chain()
+                    .distributive_run_if(resource_exists::<Session>())
+                    .in_set(OnUpdate(GameState::Lobby)),
+            )
+            .add_system(lobby_cleanup.in_schedule(OnExit(GameState::Lobby)))
+            .add_systems(
+                (receive, send)
+                    .chain()
+                    .distributive_run_if(resource_exists::<Session>()),
+            )
+            .add_system(
+                show_racers
+                    .run_if(resource_exists::<Session>())
+                    .in_set(OnUpdate(GameState::Game)),
+            )
+            .add_system(race_cleanup.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum Packet {
+    /// To the host, to join the lobby and then to stay in it.
+    Join {
+        name: String,
+        ready: bool,
+    },
+    /// From the host, `you` being the id of whoever it's sent to. The
+    /// countdown is only for show, the race starting on `Start`.
+    Lobby {
+        you: u8,
+        players: Vec<Peer>,
+        countdown: Option<f32>,
+    },
+    /// From the host to everyone it counted in, once the countdown is over.
+    Start {
+        seed: u64,
+    },
+    /// A bee mid-race. Players send their own to the host, which passes
+    /// them on to everyone else.
+    Bee(BeeState),
+    Leave,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Peer {
+    id: u8,
+    name: String,
+    ready: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+struct BeeState {
+    id: u8,
+    sample: Sample,
+    alive: bool,
+}
+
+/// A player in a hosted lobby.
+struct Client {
+    addr: SocketAddr,
+    peer: Peer,
+    heard: f64,
+    /// Sent `Start`, and sent it again until the client is heard racing.
+    starting: bool,
+}
+
+struct Racer {
+    bee: BeeState,
+    heard: f64,
+}
+
+/// Present from opening the lobby until leaving it, races included.
+#[derive(Resource)]
+pub struct Session {
+    socket: UdpSocket,
+    /// Where the host is, or `None` when hosting.
+    host: Option<SocketAddr>,
+    /// Who has joined, when hosting.
+    clients: Vec<Client>,
+    /// Set once the host has gone.
+    closed: bool,
+    id: u8,
+    name: String,
+    seed: u64,
+    ready: bool,
+    players: Vec<Peer>,
+    countdown: Option<f32>,
+    /// The other bees in the race, by id.
+    racers: BTreeMap<u8, Racer>,
+    /// When the host was last heard from.
+    heard: f64,
+    send_timer: Timer,
+}
+
+impl Session {
+    /// Hosts the lobby at `lobby` if nobody is yet, and joins it otherwise.
+    fn open(lobby: &str, name: &str, now: f64) -> io::Result<Self> {
+        let addr = lobby
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+        let (socket, host) = match UdpSocket::bind(addr) {
+            Ok(socket) => (socket, None),
+            Err(_) => {
+                let any: SocketAddr = match addr {
+                    SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+                    SocketAddr::V6(_) => ([0; 8], 0).into(),
+                };
+                (UdpSocket::bind(any)?, Some(addr))
+            }
+        };
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            host,
+            clients: Vec::new(),
+            closed: false,
+            id: 0,
+            name: name.to_string(),
+            seed: rand::random(),
+            ready: false,
+            players: Vec::new(),
+            countdown: None,
+            racers: BTreeMap::new(),
+            heard: now,
+            send_timer: Timer::from_seconds(SEND_EVERY, TimerMode::Repeating),
+        })
+    }
+
+    /// Seed of the pillars everyone in the race flies through.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn send(&self, to: SocketAddr, packet: &Packet) {
+        if let Ok(text) = ron::to_string(packet) {
+            // lost packets are made up for by the next ones
+            let _ = self.socket.send_to(text.as_bytes(), to);
+        }
+    }
+
+    fn join(&mut self, addr: SocketAddr, name: String, ready: bool, now: f64) {
+        let name: String = name.chars().take(16).collect();
+        if let Some(client) = self.clients.iter_mut().find(|c| c.addr == addr) {
+            client.peer.name = name;
+            client.peer.ready = ready;
+            client.heard = now;
+            return;
+        }
+        // joining mid-countdown would start someone not ready
+        if self.clients.len() + 1 >= MAX_PLAYERS || self.countdown.is_some() {
+            return;
+        }
+        let Some(id) =
+            (1..=MAX_PLAYERS as u8).find(|id| self.clients.iter().all(|c| c.peer.id != *id))
+        else {
+            return;
+        };
+        self.clients.push(Client {
+            addr,
+            peer: Peer { id, name, ready },
+            heard: now,
+            starting: false,
+        });
+    }
+
+    /// Leaves the lobby for the race.
+    fn start(&mut self, game_state: &mut NextState<GameState>) {
+        self.countdown = None;
+        self.ready = false;
+        self.racers.clear();
+        game_state.set(GameState::Game);
+    }
+
+    /// Everyone's score in the race so far, with `score` being ours.
+    pub fn standings(&self, score: u32, alive: bool, lang: Language) -> String {
+        let mut lines = Vec::new();
+        for peer in &self.players {
+            let (score, alive) = match self.racers.get(&peer.id) {
+                _ if peer.id == self.id => (score, alive),
+                Some(racer) => (racer.bee.sample.score, racer.bee.alive),
+                None => continue,
+            };
+            let out = if alive { "" } else { lang.tr("Out") };
+            lines.push(
+                format!("{} {} {}", peer.name, score, out)
+                    .trim()
+                    .to_string(),
+            );
+        }
+        lines.join("   ")
+    }
+}
+
+fn receive(
+    mut session: ResMut<Session>,
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let session = &mut *session;
+    let mut buf = [0; MAX_PACKET];
+
+    while let Ok((len, from)) = session.socket.recv_from(&mut buf) {
+        let Some(packet) = std::str::from_utf8(&buf[..len])
+            .ok()
+            .and_then(|text| ron::from_str::<Packet>(text).ok())
+        else {
+            continue;
+        };
+
+        match (session.host, packet) {
+            (None, Packet::Join { name, ready }) => session.join(from, name, ready, now),
+            (None, Packet::Bee(bee)) => {
+                let Some(client) = session.clients.iter_mut().find(|c| c.addr == from) else {
+                    continue;
+                };
+                client.heard = now;
+                client.starting = false;
+                // nobody flies anyone else's bee
+                if bee.id != client.peer.id {
+                    continue;
+                }
+                for client in session.clients.iter().filter(|c| c.addr != from) {
+                    session.send(client.addr, &Packet::Bee(bee));
+                }
+                session.racers.insert(bee.id, Racer { bee, heard: now });
+            }
+            (None, Packet::Leave) => session.clients.retain(|c| c.addr != from),
+            (Some(host), packet) if from == host => {
+                session.heard = now;
+                match packet {
+                    Packet::Lobby {
+                        you,
+                        players,
+                        countdown,
+                    } => {
+                        session.id = you;
+                        session.players = players;
+                        // a countdown only means anything to those waiting on it
+                        let waiting = session.ready && game_state.0 == GameState::Lobby;
+                        session.countdown = countdown.filter(|_| waiting);
+                    }
+                    // sent again until we're racing, so only the first counts
+                    Packet::Start { seed } if game_state.0 == GameState::Lobby => {
+                        session.seed = seed;
+                        session.start(&mut next_state);
+                    }
+                    Packet::Start { .. } => (),
+                    Packet::Bee(bee) => {
+                        session.racers.insert(bee.id, Racer { bee, heard: now });
+                    }
+                    Packet::Leave => session.closed = true,
+                    Packet::Join { .. } => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if session.host.is_some() && now - session.heard > TIMEOUT {
+        session.closed = true;
+    }
+    session.clients.retain(|c| now - c.heard < TIMEOUT);
+    session.racers.retain(|_, r| now - r.heard < TIMEOUT);
+}
+
+fn send(
+    mut session: ResMut<Session>,
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+    game_info: Option<Res<GameInfo>>,
+    players: Query<(&Transform, &Player)>,
+) {
+    if !session.send_timer.tick(time.delta()).just_finished() || session.closed {
+        return;
+    }
+
+    let bee = match (game_state.0 == GameState::Game, game_info) {
+        (true, Some(game_info)) => {
+            players
+                .iter()
+                .find(|(_, p)| p.index == 0)
+                .map(|(t, p)| BeeState {
+                    id: session.id,
+                    sample: Sample {
+                        x: t.translation.x,
+                        y: t.translation.y,
+                        world: p.y_pos,
+                        score: game_info.score,
+                    },
+                    alive: !game_info.is_dead(),
+                })
+        }
+        _ => None,
+    };
+
+    match session.host {
+        Some(host) => match bee {
+            Some(bee) => session.send(host, &Packet::Bee(bee)),
+            None => session.send(
+                host,
+                &Packet::Join {
+                    name: session.name.clone(),
+                    ready: session.ready,
+                },
+            ),
+        },
+        None => {
+            let mut players = vec![Peer {
+                id: 0,
+                name: session.name.clone(),
+                ready: session.ready,
+            }];
+            players.extend(session.clients.iter().map(|c| c.peer.clone()));
+            for client in &session.clients {
+                session.send(
+                    client.addr,
+                    &Packet::Lobby {
+                        you: client.peer.id,
+                        players: players.clone(),
+                        countdown: session.countdown,
+                    },
+                );
+                if client.starting {
+                    session.send(client.addr, &Packet::Start { seed: session.seed });
+                }
+                if let Some(bee) = bee {
+                    session.send(client.addr, &Packet::Bee(bee));
+                }
+            }
+            session.players = players;
+        }
+    }
+}
+
+#[derive(Component)]
+struct LobbyMarker;
+
+#[derive(Component)]
+struct LobbyText;
+
+#[derive(Component)]
+enum LobbyButton {
+    Ready,
+    Back,
+}
+
+fn lobby_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    session: Option<Res<Session>>,
+    time: Res<Time>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if session.is_none() {
+        match Session::open(&settings.lobby, &settings.name, time.elapsed_seconds_f64()) {
+            Ok(session) => commands.insert_resource(session),
+            Err(e) => {
+                error!("couldn't open the lobby at {}: {}", settings.lobby, e);
+                game_state.set(GameState::Menu);
+                return;
+            }
+        }
+    }
+
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                hdr: true,
+                ..default()
+            },
+            camera_2d: Camera2d {
+                clear_color: bevy::core_pipeline::clear_color::ClearColorConfig::Custom(
+                    settings.palette.menu_background(),
+                ),
+            },
+            projection: view::projection(),
+            ..default()
+        },
+        LobbyMarker,
+    ));
+
+    let font = asset_server.load("fonts/goodtimes.otf");
+    let lang = settings.language;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            LobbyMarker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 28.0,
+                        color: settings.palette.button_text(),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }),
+                LobbyText,
+            ));
+            for (label, button) in [
+                (lang.tr("Ready"), LobbyButton::Ready),
+                (lang.tr("Back"), LobbyButton::Back),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(320.0), Val::Px(60.0)),
+                                margin: UiRect::all(Val::Px(6.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: settings.palette.button_normal().into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 28.0,
+                                color: settings.palette.button_text(),
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn lobby_buttons(
+    mut commands: Commands,
+    mut buttons: Query<
+        (&Interaction, &mut BackgroundColor, &Children, &LobbyButton),
+        Changed<Interaction>,
+    >,
+    mut texts: Query<&mut Text>,
+    mut session: ResMut<Session>,
+    settings: Res<Settings>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let palette = settings.palette;
+    let lang = settings.language;
+
+    for (interaction, mut color, children, button) in buttons.iter_mut() {
+        *color = match interaction {
+            Interaction::Clicked => palette.button_pressed(),
+            Interaction::Hovered => palette.button_hovered(),
+            Interaction::None => palette.button_normal(),
+        }
+        .into();
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            LobbyButton::Ready if !session.closed => {
+                session.ready = !session.ready;
+                if let Ok(mut text) = texts.get_mut(children[0]) {
+                    text.sections[0].value = match session.ready {
+                        true => lang.tr("Not ready"),
+                        false => lang.tr("Ready"),
+                    }
+                    .to_string();
+                }
+            }
+            LobbyButton::Ready => (),
+            LobbyButton::Back => {
+                match session.host {
+                    Some(host) => session.send(host, &Packet::Leave),
+                    None => {
+                        for client in &session.clients {
+                            session.send(client.addr, &Packet::Leave);
+                        }
+                    }
+                }
+                commands.remove_resource::<Session>();
+                game_state.set(GameState::Menu);
+            }
+        }
+    }
+}
+
+fn lobby_update(
+    mut session: ResMut<Session>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut texts: Query<&mut Text, With<LobbyText>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let lang = settings.language;
+    let session = &mut *session;
+
+    if session.host.is_none() {
+        let everyone_ready = session.ready
+            && !session.clients.is_empty()
+            && session.clients.iter().all(|c| c.peer.ready);
+        session.countdown = match session.countdown {
+            _ if !everyone_ready => None,
+            Some(left) => Some(left),
+            None => {
+                session.seed = rand::random();
+                Some(COUNTDOWN)
+            }
+        };
+    }
+    if let Some(left) = &mut session.countdown {
+        *left = (*left - time.delta_seconds()).max(0.0);
+    }
+    // the host starts everyone, so nobody is left waiting on a countdown
+    if session.host.is_none() && session.countdown == Some(0.0) {
+        for client in session.clients.iter_mut() {
+            client.starting = true;
+            client.peer.ready = false;
+        }
+        session.start(&mut game_state);
+    }
+
+    let mut value = format!("{}  {}", lang.tr("Lobby"), settings.lobby);
+    for peer in &session.players {
+        let status = match session.racers.get(&peer.id) {
+            _ if peer.ready => lang.tr("Ready").to_string(),
+            Some(racer) if racer.bee.alive => racer.bee.sample.score.to_string(),
+            _ => String::new(),
+        };
+        value += &format!("\n{}  {}", peer.name, status);
+    }
+    value += "\n\n";
+    value += &match session.countdown {
+        _ if session.closed => lang.tr("Host left").to_string(),
+        Some(left) => format!("{}", left.ceil()),
+        None => lang.tr("Waiting for players").to_string(),
+    };
+
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn lobby_cleanup(mut commands: Commands, query: Query<Entity, With<LobbyMarker>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
+
+/// Another player's bee, by id.
+#[derive(Component)]
+struct RacerGhost(u8);
+
+#[derive(Component)]
+struct RacerLabel(u8);
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn show_racers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    session: Res<Session>,
+    settings: Res<Settings>,
+    players: Query<&Player>,
+    mut ghosts: Query<(&RacerGhost, &mut Transform, &mut Visibility)>,
+    mut labels: Query<(&RacerLabel, &mut Transform, &mut Text), Without<RacerGhost>>,
+) {
+    let world = players
+        .iter()
+        .find(|p| p.index == 0)
+        .map_or(0.0, |p| p.y_pos);
+    // the other bee's world sits at a different height, so shift it into ours
+    let place = |sample: Sample| Vec2::new(sample.x, sample.y - sample.world + world);
+
+    for (id, racer) in &session.racers {
+        if *id == session.id || ghosts.iter().any(|(g, ..)| g.0 == *id) {
+            continue;
+        }
+        let texture_atlas = texture_atlases.add(TextureAtlas::from_grid(
+            asset_server.load("textures/bee.png"),
+            Vec2::new(32.0, 32.0),
+            2,
+            1,
+            Some(Vec2::new(1.0, 0.0)),
+            None,
+        ));
+        let at = place(racer.bee.sample);
+        commands.spawn((
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    custom_size: Some(Vec2::splat(80.0)),
+                    color: Color::rgba(1.0, 1.0, 1.0, GHOST_ALPHA),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(at.extend(94.0)),
+                texture_atlas,
+                ..Default::default()
+            },
+            Animator::new(Clip::Idle),
+            RacerGhost(*id),
+        ));
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/goodtimes.otf"),
+                        font_size: 20.0,
+                        color: settings.palette.score_text().with_a(0.7),
+                    },
+                ),
+                transform: Transform::from_translation((at + Vec2::Y * 60.0).extend(131.0)),
+                ..Default::default()
+            },
+            RacerLabel(*id),
+        ));
+    }
+
+    for (ghost, mut t, mut visibility) in ghosts.iter_mut() {
+        match session.racers.get(&ghost.0).filter(|r| r.bee.alive) {
+            Some(racer) => {
+                let at = place(racer.bee.sample);
+                t.translation.x = at.x;
+                t.translation.y = at.y;
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+    for (label, mut t, mut text) in labels.iter_mut() {
+        let Some(racer) = session.racers.get(&label.0).filter(|r| r.bee.alive) else {
+            text.sections[0].value.clear();
+            continue;
+        };
+        let at = place(racer.bee.sample);
+        t.translation.x = at.x;
+        t.translation.y = at.y + 60.0;
+        let name = session
+            .players
+            .iter()
+            .find(|p| p.id == label.0)
+            .map_or("", |p| &p.name);
+        text.sections[0].value = format!("{} {}", name, racer.bee.sample.score);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn race_cleanup(
+    mut commands: Commands,
+    query: Query<Entity, Or<(With<RacerGhost>, With<RacerLabel>)>>,
+) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -0,0 +1,185 @@
+use std::ops::Range;
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    window::PrimaryWindow,
+};
+use serde::Deserialize;
+
+use crate::{
+    bee_game::{PillarShared, Player},
+    biomes::{Biome, Scenery},
+    environment::Environment,
+    view, GameState,
+};
+
+pub struct ParallaxPlugin;
+
+impl Plugin for ParallaxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Backdrop>()
+            .init_asset_loader::<BackdropLoader>()
+            .add_startup_system(load_backdrop)
+            .add_system(setup.in_schedule(OnEnter(GameState::Game)))
+            .add_system(scroll.in_set(OnUpdate(GameState::Game)))
+            .add_system(cleanup.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+pub const LAYERS: &str = "background.parallax.ron";
+
+/// A band of sprites scrolling past at a fraction of the pillars' speed.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Layer {
+    pub texture: String,
+    pub count: usize,
+    pub size: Vec2,
+    pub scale: Range<f32>,
+    pub color: Color,
+    pub z: f32,
+    /// Fraction of `PillarShared::x_vel` the layer scrolls at.
+    pub speed: Range<f32>,
+    /// Fraction of `Player::y_pos` the layer follows the world up and down.
+    pub rise: f32,
+    /// Vertical band the sprites are scattered over, as a fraction of the
+    /// visible height with 0 at the centre.
+    pub band: Range<f32>,
+    /// Where the layer shows, fading in and out as biomes change. Empty
+    /// for everywhere.
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+}
+
+/// Back to front. A layer with a single sprite sits still in the middle of
+/// the screen instead of scrolling and wrapping.
+#[derive(Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "4e8b2d17-9c3a-4f60-b5d8-71a0e6c2f934"]
+pub struct Backdrop {
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Default)]
+struct BackdropLoader;
+
+impl AssetLoader for BackdropLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let backdrop: Backdrop = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(backdrop));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["parallax.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct BackdropHandle(Handle<Backdrop>);
+
+/// How far past the screen edge sprites go before wrapping round.
+const MARGIN: f32 = 400.0;
+
+#[derive(Component)]
+struct ParallaxMarker;
+
+#[derive(Component)]
+struct ParallaxSprite {
+    color: Color,
+    speed: f32,
+    rise: f32,
+    base_y: f32,
+    biomes: Vec<Biome>,
+}
+
+fn random_in(range: &Range<f32>) -> f32 {
+    range.start + rand::random::<f32>() * (range.end - range.start)
+}
+
+fn load_backdrop(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(BackdropHandle(asset_server.load(LAYERS)));
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    backdrop: Res<BackdropHandle>,
+    backdrops: Res<Assets<Backdrop>>,
+    wins: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Some(backdrop) = backdrops.get(&backdrop.0) else {
+        return;
+    };
+    let size = view::primary_visible_size(&wins);
+    let width = size.x + MARGIN * 2.0;
+
+    for layer in &backdrop.layers {
+        for _ in 0..layer.count {
+            let x = match layer.count {
+                1 => 0.0,
+                _ => (rand::random::<f32>() - 0.5) * width,
+            };
+            let base_y = random_in(&layer.band) * size.y;
+
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(layer.size),
+                        color: layer.color,
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(x, base_y, layer.z)
+                        .with_scale(Vec3::splat(random_in(&layer.scale))),
+                    texture: asset_server.load(layer.texture.as_str()),
+                    ..Default::default()
+                },
+                ParallaxSprite {
+                    color: layer.color,
+                    speed: random_in(&layer.speed),
+                    rise: layer.rise,
+                    base_y,
+                    biomes: layer.biomes.clone(),
+                },
+                ParallaxMarker,
+            ));
+        }
+    }
+}
+
+fn scroll(
+    mut query: Query<(&mut Transform, &mut Sprite, &ParallaxSprite)>,
+    pillar_shared: Res<PillarShared>,
+    players: Query<&Player>,
+    env: Res<Environment>,
+    scenery: Res<Scenery>,
+    wins: Query<&Window, With<PrimaryWindow>>,
+    time: Res<Time>,
+) {
+    let half = view::primary_visible_size(&wins).x / 2.0 + MARGIN;
+    // the scenery is shared, so split the difference between worlds
+    let count = players.iter().count().max(1) as f32;
+    let y_pos = players.iter().map(|p| p.y_pos).sum::<f32>() / count;
+
+    for (mut t, mut sprite, p) in query.iter_mut() {
+        t.translation.x += pillar_shared.x_vel * p.speed * time.delta_seconds();
+        if t.translation.x > half {
+            t.translation.x -= half * 2.0;
+        }
+        t.translation.y = p.base_y + y_pos * p.rise;
+        let tinted = p.color * Vec4::from(env.tint);
+        sprite.color = tinted.with_a(p.color.a() * scenery.presence(&p.biomes));
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<ParallaxMarker>>) {
+    for e in query.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -0,0 +1,295 @@
+use std::{ops::Range, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{scores::NewHighScore, GameState};
+
+/// Particle sprites are spawned once up front and recycled, so a burst never
+/// costs more than flipping some visibilities.
+const POOL_SIZE: usize = 512;
+
+// colours above 1.0 glow under the game camera's bloom
+const PUFF_COLORS: &[Color] = &[Color::rgba(1.0, 1.0, 1.0, 0.8)];
+const POLLEN_COLORS: &[Color] = &[Color::rgb(2.5, 2.0, 0.4), Color::rgb(1.8, 1.4, 0.2)];
+const SPARK_COLORS: &[Color] = &[Color::rgb(4.0, 2.5, 0.6), Color::rgb(4.0, 1.2, 0.3)];
+const RAIN_COLORS: &[Color] = &[Color::rgba(0.7, 0.8, 1.0, 0.6)];
+const CONFETTI_COLORS: &[Color] = &[
+    Color::rgb(1.5, 0.3, 0.3),
+    Color::rgb(0.3, 1.5, 0.4),
+    Color::rgb(0.3, 0.5, 1.8),
+    Color::rgb(1.8, 1.5, 0.2),
+    Color::rgb(1.5, 0.4, 1.5),
+];
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnParticles>()
+            .init_resource::<ParticlePool>()
+            .add_startup_system(pool_setup)
+            .add_system(high_score_confetti)
+            .add_system(emitters.before(spawn_bursts))
+            .add_system(spawn_bursts.after(high_score_confetti))
+            .add_system(update_particles.after(spawn_bursts))
+            .add_system(reset.in_schedule(OnExit(GameState::Game)));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParticleKind {
+    Puff,
+    Pollen,
+    Spark,
+    Confetti,
+    Rain,
+}
+
+struct ParticleDef {
+    count: usize,
+    lifetime: Range<f32>,
+    speed: Range<f32>,
+    /// Direction of travel in radians, 0 pointing right.
+    angle: Range<f32>,
+    /// Constant velocity added on top, e.g. to drift with the world.
+    drift: Vec2,
+    gravity: f32,
+    size: Range<f32>,
+    colors: &'static [Color],
+}
+
+impl ParticleKind {
+    fn def(self) -> ParticleDef {
+        use std::f32::consts::{PI, TAU};
+        use ParticleKind::*;
+        match self {
+            Puff => ParticleDef {
+                count: 8,
+                lifetime: 0.25..0.45,
+                speed: 40.0..90.0,
+                angle: PI * 0.25..PI * 0.75,
+                drift: Vec2::new(0.0, -60.0),
+                gravity: 0.0,
+                size: 6.0..12.0,
+                colors: PUFF_COLORS,
+            },
+            Pollen => ParticleDef {
+                count: 1,
+                lifetime: 0.6..1.0,
+                speed: 5.0..20.0,
+                angle: 0.0..TAU,
+                drift: Vec2::new(150.0, 0.0),
+                gravity: -20.0,
+                size: 3.0..5.0,
+                colors: POLLEN_COLORS,
+            },
+            Spark => ParticleDef {
+                count: 24,
+                lifetime: 0.3..0.7,
+                speed: 150.0..400.0,
+                angle: 0.0..TAU,
+                drift: Vec2::ZERO,
+                gravity: -600.0,
+                size: 3.0..6.0,
+                colors: SPARK_COLORS,
+            },
+            Confetti => ParticleDef {
+                count: 80,
+                lifetime: 1.5..2.5,
+                speed: 100.0..350.0,
+                angle: PI * 1.1..PI * 1.9,
+                drift: Vec2::ZERO,
+                gravity: -150.0,
+                size: 6.0..10.0,
+                colors: CONFETTI_COLORS,
+            },
+            Rain => ParticleDef {
+                count: 1,
+                lifetime: 1.2..1.6,
+                speed: 600.0..750.0,
+                angle: PI * 1.45..PI * 1.5,
+                drift: Vec2::ZERO,
+                gravity: 0.0,
+                size: 2.0..4.0,
+                colors: RAIN_COLORS,
+            },
+        }
+    }
+}
+
+/// A one-off burst at a point in world space.
+pub struct SpawnParticles {
+    pub kind: ParticleKind,
+    pub at: Vec2,
+}
+
+/// Emits a steady stream of particles from its entity.
+#[derive(Component)]
+pub struct ParticleEmitter {
+    pub kind: ParticleKind,
+    pub per_second: f32,
+    pub offset: Vec2,
+    owed: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(kind: ParticleKind, per_second: f32, offset: Vec2) -> Self {
+        Self {
+            kind,
+            per_second,
+            offset,
+            owed: 0.0,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Particle {
+    vel: Vec2,
+    gravity: f32,
+    size: f32,
+    alpha: f32,
+    life: Timer,
+}
+
+#[derive(Resource, Default)]
+struct ParticlePool {
+    free: Vec<Entity>,
+}
+
+fn random_in(range: &Range<f32>) -> f32 {
+    range.start + rand::random::<f32>() * (range.end - range.start)
+}
+
+fn pool_setup(mut commands: Commands, mut pool: ResMut<ParticlePool>) {
+    for _ in 0..POOL_SIZE {
+        let e = commands
+            .spawn((
+                SpriteBundle {
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                },
+                Particle {
+                    vel: Vec2::ZERO,
+                    gravity: 0.0,
+                    size: 0.0,
+                    alpha: 0.0,
+                    life: Timer::default(),
+                },
+            ))
+            .id();
+        pool.free.push(e);
+    }
+}
+
+fn emitters(
+    mut query: Query<(&GlobalTransform, &mut ParticleEmitter)>,
+    mut bursts: EventWriter<SpawnParticles>,
+    time: Res<Time>,
+) {
+    for (t, mut emitter) in query.iter_mut() {
+        emitter.owed += emitter.per_second * time.delta_seconds();
+        while emitter.owed >= 1.0 {
+            emitter.owed -= 1.0;
+            bursts.send(SpawnParticles {
+                kind: emitter.kind,
+                at: t.translation().truncate() + emitter.offset,
+            });
+        }
+    }
+}
+
+fn high_score_confetti(
+    mut events: EventReader<NewHighScore>,
+    mut bursts: EventWriter<SpawnParticles>,
+) {
+    if events.iter().count() > 0 {
+        bursts.send(SpawnParticles {
+            kind: ParticleKind::Confetti,
+            at: Vec2::new(0.0, 400.0),
+        });
+    }
+}
+
+fn spawn_bursts(
+    mut events: EventReader<SpawnParticles>,
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(&mut Particle, &mut Transform, &mut Sprite, &mut Visibility)>,
+) {
+    for event in events.iter() {
+        let def = event.kind.def();
+        for _ in 0..def.count {
+            // when the pool runs dry new particles are simply dropped
+            let Some(e) = pool.free.pop() else {
+                return;
+            };
+            let Ok((mut p, mut t, mut sprite, mut visibility)) = particles.get_mut(e) else {
+                continue;
+            };
+
+            let angle = random_in(&def.angle);
+            let color = def.colors[rand::random::<usize>() % def.colors.len()];
+
+            p.vel = Vec2::from_angle(angle) * random_in(&def.speed) + def.drift;
+            p.gravity = def.gravity;
+            p.size = random_in(&def.size);
+            p.alpha = color.a();
+            p.life = Timer::new(
+                Duration::from_secs_f32(random_in(&def.lifetime)),
+                TimerMode::Once,
+            );
+
+            t.translation = event.at.extend(700.0);
+            sprite.color = color;
+            sprite.custom_size = Some(Vec2::splat(p.size));
+            *visibility = Visibility::Visible;
+        }
+    }
+}
+
+fn update_particles(
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+
+    for (e, mut p, mut t, mut sprite, mut visibility) in particles.iter_mut() {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+
+        if p.life.tick(time.delta()).finished() {
+            *visibility = Visibility::Hidden;
+            pool.free.push(e);
+            continue;
+        }
+
+        p.vel.y += p.gravity * dt;
+        t.translation += (p.vel * dt).extend(0.0);
+
+        let left = p.life.percent_left();
+        sprite.color.set_a(p.alpha * left);
+        sprite.custom_size = Some(Vec2::splat(p.size * (0.5 + 0.5 * left)));
+    }
+}
+
+/// Puts every live particle back in the pool, so nothing from a run is left
+/// drifting over the screens after it.
+fn reset(
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(Entity, &mut Visibility), With<Particle>>,
+) {
+    for (e, mut visibility) in particles.iter_mut() {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+            pool.free.push(e);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{modes::GameMode, storage};
+
+const SCORES_FILE: &str = "scores.ron";
+
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct HighScores {
+    best: BTreeMap<GameMode, u32>,
+}
+
+impl HighScores {
+    pub fn best(&self, mode: GameMode) -> u32 {
+        self.best.get(&mode).copied().unwrap_or(0)
+    }
+
+    pub fn set_best(&mut self, mode: GameMode, score: u32) {
+        self.best.insert(mode, score);
+    }
+
+    pub fn load() -> Self {
+        storage::load(SCORES_FILE)
+    }
+
+    pub fn save(&self) {
+        storage::save(SCORES_FILE, self);
+    }
+}
+
+/// Sent the moment a run beats the previous best score.
+pub struct NewHighScore;
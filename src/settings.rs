@@ -0,0 +1,545 @@
+use bevy::{
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(apply_settings.run_if(resource_changed::<Settings>()));
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub screen_shake: bool,
+    /// Race a recording of the best run, flying its pillars again instead
+    /// of new ones.
+    pub ghost: bool,
+    pub controls: Controls,
+    pub palette: Palette,
+    pub language: Language,
+    /// Shown on the leaderboard.
+    pub name: String,
+    /// `host:port` of the leaderboard server, or empty to stay offline.
+    pub leaderboard: String,
+    /// `host:port` of the online lobby. The first game there hosts it.
+    pub lobby: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            music_volume: 0.8,
+            sfx_volume: 1.0,
+            fullscreen: false,
+            vsync: true,
+            screen_shake: true,
+            ghost: false,
+            controls: Controls::World,
+            palette: Palette::Standard,
+            language: Language::English,
+            name: "Bee".to_string(),
+            leaderboard: "127.0.0.1:7878".to_string(),
+            lobby: "127.0.0.1:7880".to_string(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        storage::load(SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        storage::save(SETTINGS_FILE, self);
+    }
+
+    pub fn music(&self) -> f64 {
+        (self.master_volume * self.music_volume) as f64
+    }
+
+    pub fn sfx(&self) -> f64 {
+        (self.master_volume * self.sfx_volume) as f64
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self.fullscreen {
+            true => WindowMode::BorderlessFullscreen,
+            false => WindowMode::Windowed,
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        match self.vsync {
+            true => PresentMode::AutoVsync,
+            false => PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+/// Steps a 0..=1 volume up by 10%, wrapping back round to silent.
+pub fn step_volume(volume: f32) -> f32 {
+    let step = (volume * 10.0).round() as i32 + 1;
+    (step % 11) as f32 / 10.0
+}
+
+/// How a flap moves things: the original shifts the world around a wandering
+/// bee, classic gives the bee its own velocity and gravity.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Controls {
+    World,
+    Classic,
+}
+
+impl Controls {
+    pub fn next(self) -> Self {
+        match self {
+            Controls::World => Controls::Classic,
+            Controls::Classic => Controls::World,
+        }
+    }
+
+    /// English name, to be passed through `Language::tr`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Controls::World => "Moving world",
+            Controls::Classic => "Classic",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    Standard,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl Palette {
+    pub fn next(self) -> Self {
+        use Palette::*;
+        match self {
+            Standard => Deuteranopia,
+            Deuteranopia => Protanopia,
+            Protanopia => Tritanopia,
+            Tritanopia => Standard,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        use Palette::*;
+        match self {
+            Standard => "Standard",
+            Deuteranopia => "Deuteranopia",
+            Protanopia => "Protanopia",
+            Tritanopia => "Tritanopia",
+        }
+    }
+
+    pub fn sky(self) -> Color {
+        use Palette::*;
+        match self {
+            Standard => Color::rgb(0.35, 0.8, 1.0),
+            Deuteranopia | Protanopia => Color::rgb(0.45, 0.7, 1.0),
+            Tritanopia => Color::rgb(0.55, 0.85, 0.9),
+        }
+    }
+
+    pub fn menu_background(self) -> Color {
+        use Palette::*;
+        match self {
+            Standard => Color::rgb(1.0, 0.95, 0.84),
+            Deuteranopia | Protanopia => Color::rgb(0.94, 0.95, 1.0),
+            Tritanopia => Color::rgb(1.0, 0.94, 0.94),
+        }
+    }
+
+    pub fn score_text(self) -> Color {
+        use Palette::*;
+        match self {
+            Standard => Color::rgb(0.9, 0.8, 0.5),
+            Deuteranopia | Protanopia => Color::rgb(1.0, 0.85, 0.3),
+            Tritanopia => Color::rgb(1.0, 0.6, 0.6),
+        }
+    }
+
+    pub fn button_text(self) -> Color {
+        use Palette::*;
+        match self {
+            Standard => Color::rgb(0.0, 0.08, 0.20),
+            Deuteranopia | Protanopia => Color::rgb(0.0, 0.05, 0.3),
+            Tritanopia => Color::rgb(0.2, 0.0, 0.05),
+        }
+    }
+
+    pub fn button_normal(self) -> Color {
+        use Palette::*;
+        match self {
+            Standard => Color::rgb(1.0, 0.92, 0.80),
+            Deuteranopia | Protanopia => Color::rgb(0.88, 0.9, 0.97),
+            Tritanopia => Color::rgb(0.97, 0.88, 0.88),
+        }
+    }
+
+    pub fn button_hovered(self) -> Color {
+        use Palette::*;
+        match self {
+            Standard => Color::rgb(1.0, 0.94, 0.57),
+            Deuteranopia | Protanopia => Color::rgb(0.75, 0.85, 1.0),
+            Tritanopia => Color::rgb(1.0, 0.75, 0.75),
+        }
+    }
+
+    pub fn button_pressed(self) -> Color {
+        use Palette::*;
+        match self {
+            Standard => Color::rgb(1.0, 0.84, 0.48),
+            Deuteranopia | Protanopia => Color::rgb(0.45, 0.65, 1.0),
+            Tritanopia => Color::rgb(0.95, 0.45, 0.45),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    English,
+    French,
+    German,
+    Spanish,
+}
+
+impl Language {
+    pub fn next(self) -> Self {
+        use Language::*;
+        match self {
+            English => French,
+            French => German,
+            German => Spanish,
+            Spanish => English,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        use Language::*;
+        match self {
+            English => "English",
+            French => "Français",
+            German => "Deutsch",
+            Spanish => "Español",
+        }
+    }
+
+    /// Looks up the translation of an English UI string, falling back to the
+    /// English text when there isn't one.
+    pub fn tr(self, text: &'static str) -> &'static str {
+        use Language::*;
+        match (self, text) {
+            (French, "Play") => "Jouer",
+            (French, "Options") => "Options",
+            (French, "Exit") => "Quitter",
+            (French, "Back") => "Retour",
+            (French, "Master") => "Général",
+            (French, "Music") => "Musique",
+            (French, "Effects") => "Effets",
+            (French, "Display") => "Affichage",
+            (French, "Fullscreen") => "Plein écran",
+            (French, "Windowed") => "Fenêtré",
+            (French, "Vsync") => "Synchro V",
+            (French, "Screen shake") => "Tremblement",
+            (French, "Controls") => "Contrôles",
+            (French, "Moving world") => "Monde mobile",
+            (French, "Classic") => "Classique",
+            (French, "Palette") => "Palette",
+            (French, "Language") => "Langue",
+            (French, "Endless") => "Infini",
+            (French, "Time Attack") => "Contre la montre",
+            (French, "Sudden Death") => "Mort subite",
+            (French, "Bounces") => "Rebonds",
+            (French, "Best") => "Record",
+            (French, "Daily") => "Défi du jour",
+            (French, "Practice") => "Entraînement",
+            (French, "Dailies") => "Défis",
+            (French, "Level") => "Niveau",
+            (French, "Levels") => "Niveaux",
+            (French, "Editor") => "Éditeur",
+            (French, "Versus") => "Versus",
+            (French, "Player 1 wins") => "Joueur 1 gagne",
+            (French, "Player 2 wins") => "Joueur 2 gagne",
+            (French, "Draw") => "Égalité",
+            (French, "Race ghost") => "Défier le fantôme",
+            (French, "Leaderboard") => "Classement",
+            (French, "Offline") => "Hors ligne",
+            (French, "Rank") => "Rang",
+            (French, "Not verified") => "Non vérifié",
+            (French, "Online") => "En ligne",
+            (French, "Lobby") => "Salon",
+            (French, "Ready") => "Prêt",
+            (French, "Not ready") => "Pas prêt",
+            (French, "Waiting for players") => "En attente de joueurs",
+            (French, "Host left") => "L'hôte est parti",
+            (French, "Out") => "Éliminé",
+            (French, "Achievements") => "Succès",
+            (French, "Achievement unlocked") => "Succès débloqué",
+            (French, "Runs") => "Parties",
+            (French, "Flaps") => "Battements",
+            (French, "Pillars") => "Piliers",
+            (French, "Pollen") => "Pollen",
+            (French, "Longest run") => "Plus longue partie",
+            (French, "Deaths") => "Morts",
+            (French, "Floor") => "Sol",
+            (French, "Pipe") => "Tuyau",
+            (French, "Narrow pipe") => "Tuyau étroit",
+            (French, "Wide pipe") => "Tuyau large",
+            (French, "First Flight") => "Premier envol",
+            (French, "Getting Going") => "C'est parti",
+            (French, "Hive Mind") => "Esprit de ruche",
+            (French, "Glider") => "Planeur",
+            (French, "Close Shave") => "De justesse",
+            (French, "Marathon") => "Marathon",
+            (French, "Pollinator") => "Pollinisateur",
+            (French, "Bouncy") => "Rebondissant",
+            (French, "Pass a pillar") => "Passer un pilier",
+            (French, "Pass 10 pillars in a run") => "Passer 10 piliers en une partie",
+            (French, "Pass 50 pillars in a run") => "Passer 50 piliers en une partie",
+            (French, "Pass 10 pillars, then go 2 s without flapping") => {
+                "Passer 10 piliers, puis planer 2 s sans battre des ailes"
+            }
+            (French, "Scrape past 5 pillars in a run") => "Frôler 5 piliers en une partie",
+            (French, "Fly for 2 minutes in a run") => "Voler 2 minutes en une partie",
+            (French, "Collect 25 pollen") => "Récolter 25 pollens",
+            (French, "Bounce off 20 pillars in a run") => "Rebondir sur 20 piliers en une partie",
+            (French, "Skins") => "Apparences",
+            (French, "Worn") => "Portée",
+            (French, "Sketch") => "Croquis",
+            (French, "Rose") => "Rose",
+            (French, "Golden") => "Doré",
+            (French, "Midnight") => "Minuit",
+            (French, "Meadow") => "Prairie",
+            (French, "Greenhouse") => "Serre",
+            (French, "City") => "Ville",
+            (French, "Hive") => "Ruche",
+            (French, "Boss") => "Boss",
+            (French, "On") => "Oui",
+            (French, "Off") => "Non",
+
+            (German, "Play") => "Spielen",
+            (German, "Options") => "Optionen",
+            (German, "Exit") => "Beenden",
+            (German, "Back") => "Zurück",
+            (German, "Master") => "Gesamt",
+            (German, "Music") => "Musik",
+            (German, "Effects") => "Effekte",
+            (German, "Display") => "Anzeige",
+            (German, "Fullscreen") => "Vollbild",
+            (German, "Windowed") => "Fenster",
+            (German, "Vsync") => "VSync",
+            (German, "Screen shake") => "Wackeln",
+            (German, "Controls") => "Steuerung",
+            (German, "Moving world") => "Bewegte Welt",
+            (German, "Classic") => "Klassisch",
+            (German, "Palette") => "Farben",
+            (German, "Language") => "Sprache",
+            (German, "Mode") => "Modus",
+            (German, "Endless") => "Endlos",
+            (German, "Time Attack") => "Zeitrennen",
+            (German, "Bounces") => "Abpraller",
+            (German, "Best") => "Rekord",
+            (German, "Daily") => "Tagesaufgabe",
+            (German, "Practice") => "Training",
+            (German, "Dailies") => "Tagesaufgaben",
+            (German, "Levels") => "Level",
+            (German, "Versus") => "Versus",
+            (German, "Player 1 wins") => "Spieler 1 gewinnt",
+            (German, "Player 2 wins") => "Spieler 2 gewinnt",
+            (German, "Draw") => "Unentschieden",
+            (German, "Race ghost") => "Geisterrennen",
+            (German, "Leaderboard") => "Bestenliste",
+            (German, "Offline") => "Offline",
+            (German, "Rank") => "Platz",
+            (German, "Not verified") => "Nicht bestätigt",
+            (German, "Online") => "Online",
+            (German, "Lobby") => "Lobby",
+            (German, "Ready") => "Bereit",
+            (German, "Not ready") => "Nicht bereit",
+            (German, "Waiting for players") => "Warte auf Spieler",
+            (German, "Host left") => "Host hat verlassen",
+            (German, "Out") => "Raus",
+            (German, "Achievements") => "Erfolge",
+            (German, "Achievement unlocked") => "Erfolg freigeschaltet",
+            (German, "Runs") => "Runden",
+            (German, "Flaps") => "Flügelschläge",
+            (German, "Pillars") => "Säulen",
+            (German, "Pollen") => "Pollen",
+            (German, "Longest run") => "Längste Runde",
+            (German, "Deaths") => "Tode",
+            (German, "Floor") => "Boden",
+            (German, "Pipe") => "Rohr",
+            (German, "Narrow pipe") => "Enges Rohr",
+            (German, "Wide pipe") => "Breites Rohr",
+            (German, "First Flight") => "Erster Flug",
+            (German, "Getting Going") => "Es geht los",
+            (German, "Hive Mind") => "Schwarmgeist",
+            (German, "Glider") => "Gleiter",
+            (German, "Close Shave") => "Knapp vorbei",
+            (German, "Marathon") => "Marathon",
+            (German, "Pollinator") => "Bestäuber",
+            (German, "Bouncy") => "Hüpfer",
+            (German, "Pass a pillar") => "Eine Säule passieren",
+            (German, "Pass 10 pillars in a run") => "10 Säulen in einer Runde passieren",
+            (German, "Pass 50 pillars in a run") => "50 Säulen in einer Runde passieren",
+            (German, "Pass 10 pillars, then go 2 s without flapping") => {
+                "10 Säulen passieren, dann 2 s ohne Flügelschlag gleiten"
+            }
+            (German, "Scrape past 5 pillars in a run") => "5 Säulen in einer Runde knapp streifen",
+            (German, "Fly for 2 minutes in a run") => "2 Minuten in einer Runde fliegen",
+            (German, "Collect 25 pollen") => "25 Pollen sammeln",
+            (German, "Bounce off 20 pillars in a run") => {
+                "In einer Runde 20 Mal von Säulen abprallen"
+            }
+            (German, "Skins") => "Skins",
+            (German, "Worn") => "Getragen",
+            (German, "Sketch") => "Skizze",
+            (German, "Rose") => "Rosa",
+            (German, "Golden") => "Golden",
+            (German, "Midnight") => "Mitternacht",
+            (German, "Meadow") => "Wiese",
+            (German, "Greenhouse") => "Gewächshaus",
+            (German, "City") => "Stadt",
+            (German, "Hive") => "Bienenstock",
+            (German, "Boss") => "Boss",
+            (German, "On") => "An",
+            (German, "Off") => "Aus",
+
+            (Spanish, "Play") => "Jugar",
+            (Spanish, "Options") => "Opciones",
+            (Spanish, "Exit") => "Salir",
+            (Spanish, "Back") => "Volver",
+            (Spanish, "Master") => "General",
+            (Spanish, "Music") => "Música",
+            (Spanish, "Effects") => "Efectos",
+            (Spanish, "Display") => "Pantalla",
+            (Spanish, "Fullscreen") => "Completa",
+            (Spanish, "Windowed") => "Ventana",
+            (Spanish, "Vsync") => "Vsync",
+            (Spanish, "Screen shake") => "Temblor",
+            (Spanish, "Controls") => "Controles",
+            (Spanish, "Moving world") => "Mundo móvil",
+            (Spanish, "Classic") => "Clásico",
+            (Spanish, "Palette") => "Colores",
+            (Spanish, "Language") => "Idioma",
+            (Spanish, "Mode") => "Modo",
+            (Spanish, "Endless") => "Infinito",
+            (Spanish, "Time Attack") => "Contrarreloj",
+            (Spanish, "Sudden Death") => "Muerte súbita",
+            (Spanish, "Bounces") => "Rebotes",
+            (Spanish, "Best") => "Récord",
+            (Spanish, "Daily") => "Reto diario",
+            (Spanish, "Practice") => "Práctica",
+            (Spanish, "Dailies") => "Retos",
+            (Spanish, "Level") => "Nivel",
+            (Spanish, "Levels") => "Niveles",
+            (Spanish, "Editor") => "Editor",
+            (Spanish, "Versus") => "Versus",
+            (Spanish, "Player 1 wins") => "Gana el jugador 1",
+            (Spanish, "Player 2 wins") => "Gana el jugador 2",
+            (Spanish, "Draw") => "Empate",
+            (Spanish, "Race ghost") => "Retar al fantasma",
+            (Spanish, "Leaderboard") => "Clasificación",
+            (Spanish, "Offline") => "Sin conexión",
+            (Spanish, "Rank") => "Puesto",
+            (Spanish, "Not verified") => "No verificado",
+            (Spanish, "Online") => "En línea",
+            (Spanish, "Lobby") => "Sala",
+            (Spanish, "Ready") => "Listo",
+            (Spanish, "Not ready") => "No listo",
+            (Spanish, "Waiting for players") => "Esperando jugadores",
+            (Spanish, "Host left") => "El anfitrión se fue",
+            (Spanish, "Out") => "Fuera",
+            (Spanish, "Achievements") => "Logros",
+            (Spanish, "Achievement unlocked") => "Logro desbloqueado",
+            (Spanish, "Runs") => "Partidas",
+            (Spanish, "Flaps") => "Aleteos",
+            (Spanish, "Pillars") => "Pilares",
+            (Spanish, "Pollen") => "Polen",
+            (Spanish, "Longest run") => "Partida más larga",
+            (Spanish, "Deaths") => "Muertes",
+            (Spanish, "Floor") => "Suelo",
+            (Spanish, "Pipe") => "Tubo",
+            (Spanish, "Narrow pipe") => "Tubo estrecho",
+            (Spanish, "Wide pipe") => "Tubo ancho",
+            (Spanish, "First Flight") => "Primer vuelo",
+            (Spanish, "Getting Going") => "En marcha",
+            (Spanish, "Hive Mind") => "Mente colmena",
+            (Spanish, "Glider") => "Planeador",
+            (Spanish, "Close Shave") => "Por los pelos",
+            (Spanish, "Marathon") => "Maratón",
+            (Spanish, "Pollinator") => "Polinizador",
+            (Spanish, "Bouncy") => "Rebotón",
+            (Spanish, "Pass a pillar") => "Pasar un pilar",
+            (Spanish, "Pass 10 pillars in a run") => "Pasar 10 pilares en una partida",
+            (Spanish, "Pass 50 pillars in a run") => "Pasar 50 pilares en una partida",
+            (Spanish, "Pass 10 pillars, then go 2 s without flapping") => {
+                "Pasar 10 pilares y planear 2 s sin aletear"
+            }
+            (Spanish, "Scrape past 5 pillars in a run") => "Rozar 5 pilares en una partida",
+            (Spanish, "Fly for 2 minutes in a run") => "Volar 2 minutos en una partida",
+            (Spanish, "Collect 25 pollen") => "Recoger 25 de polen",
+            (Spanish, "Bounce off 20 pillars in a run") => "Rebotar en 20 pilares en una partida",
+            (Spanish, "Skins") => "Aspectos",
+            (Spanish, "Worn") => "Puesto",
+            (Spanish, "Sketch") => "Boceto",
+            (Spanish, "Rose") => "Rosa",
+            (Spanish, "Golden") => "Dorado",
+            (Spanish, "Midnight") => "Medianoche",
+            (Spanish, "Meadow") => "Prado",
+            (Spanish, "Greenhouse") => "Invernadero",
+            (Spanish, "City") => "Ciudad",
+            (Spanish, "Hive") => "Colmena",
+            (Spanish, "Boss") => "Jefe",
+            (Spanish, "On") => "Sí",
+            (Spanish, "Off") => "No",
+
+            (_, text) => text,
+        }
+    }
+
+    pub fn on_off(self, value: bool) -> &'static str {
+        match value {
+            true => self.tr("On"),
+            false => self.tr("Off"),
+        }
+    }
+}
+
+fn apply_settings(settings: Res<Settings>, mut wins: Query<&mut Window, With<PrimaryWindow>>) {
+    // just loaded, and the window was opened with it
+    if settings.is_added() {
+        return;
+    }
+
+    if let Ok(mut window) = wins.get_single_mut() {
+        if window.mode != settings.window_mode() {
+            window.mode = settings.window_mode();
+        }
+        if window.present_mode != settings.present_mode() {
+            window.present_mode = settings.present_mode();
+        }
+    }
+
+    settings.save();
+}
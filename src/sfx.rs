@@ -0,0 +1,223 @@
+use std::{collections::HashMap, ops::Range, time::Duration};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_kira_audio::{
+    AudioChannel, AudioControl, AudioInstance, AudioTween, PlayAudioCommand, PlaybackState,
+};
+use rand::Rng;
+
+use crate::{audio::Sfx, settings::Settings, view};
+
+pub struct SfxPlugin;
+
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySfx>()
+            .init_resource::<Voices>()
+            .add_system(sfx_player)
+            .add_system(emitter_start)
+            .add_system(emitter_update.after(emitter_start))
+            .add_system(emitter_stop);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SoundEffect {
+    Flap,
+    Death,
+    Propeller,
+}
+
+struct SfxDef {
+    variants: &'static [&'static str],
+    volume: Range<f64>,
+    pitch: Range<f64>,
+    max_voices: usize,
+}
+
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 3] = [
+        SoundEffect::Flap,
+        SoundEffect::Death,
+        SoundEffect::Propeller,
+    ];
+
+    pub fn variants(self) -> &'static [&'static str] {
+        self.def().variants
+    }
+
+    fn def(self) -> SfxDef {
+        use SoundEffect::*;
+        match self {
+            Flap => SfxDef {
+                variants: &["sounds/beep.wav"],
+                volume: 0.7..1.0,
+                pitch: 0.9..1.15,
+                max_voices: 3,
+            },
+            Death => SfxDef {
+                variants: &["sounds/dead.wav"],
+                volume: 0.9..1.0,
+                pitch: 0.95..1.05,
+                max_voices: 1,
+            },
+            // there's no proper hum recording yet, a slowed down beep stands in
+            Propeller => SfxDef {
+                variants: &["sounds/beep.wav"],
+                volume: 0.08..0.12,
+                pitch: 0.3..0.4,
+                max_voices: 4,
+            },
+        }
+    }
+}
+
+/// Plays a one-shot effect, panned to `x` in world space when given.
+pub struct PlaySfx {
+    pub effect: SoundEffect,
+    pub x: Option<f32>,
+}
+
+impl PlaySfx {
+    pub fn at(effect: SoundEffect, x: f32) -> Self {
+        Self { effect, x: Some(x) }
+    }
+}
+
+/// Loops an effect for as long as the entity lives, panning it and fading it
+/// with the entity's distance from the middle of the screen.
+#[derive(Component)]
+pub struct SfxEmitter {
+    pub effect: SoundEffect,
+    volume: f64,
+}
+
+impl SfxEmitter {
+    pub fn new(effect: SoundEffect) -> Self {
+        Self {
+            effect,
+            volume: 0.0,
+        }
+    }
+}
+
+/// Instances currently playing for each effect, oldest first, plus the looping
+/// instance owned by each emitter.
+#[derive(Resource, Default)]
+struct Voices {
+    one_shots: HashMap<SoundEffect, Vec<Handle<AudioInstance>>>,
+    emitters: HashMap<Entity, (SoundEffect, Handle<AudioInstance>)>,
+}
+
+/// Maps a world x position onto kira's 0 (left) to 1 (right) panning.
+fn panning(x: f32, wins: &Query<&Window, With<PrimaryWindow>>) -> f64 {
+    let width = view::primary_visible_size(wins).x;
+    (0.5 + x / width).clamp(0.0, 1.0) as f64
+}
+
+fn play<'a>(
+    effect: SoundEffect,
+    sfx: &'a AudioChannel<Sfx>,
+    asset_server: &AssetServer,
+    settings: &Settings,
+) -> (PlayAudioCommand<'a>, f64) {
+    let def = effect.def();
+    let mut rng = rand::thread_rng();
+    let path = def.variants[rng.gen_range(0..def.variants.len())];
+    let volume = rng.gen_range(def.volume) * settings.sfx();
+
+    let mut command = sfx.play(asset_server.load(path));
+    command
+        .with_volume(volume)
+        .with_playback_rate(rng.gen_range(def.pitch));
+    (command, volume)
+}
+
+fn sfx_player(
+    mut events: EventReader<PlaySfx>,
+    mut voices: ResMut<Voices>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    sfx: Res<AudioChannel<Sfx>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    wins: Query<&Window, With<PrimaryWindow>>,
+) {
+    for event in events.iter() {
+        let playing = voices.one_shots.entry(event.effect).or_default();
+        playing.retain(|h| !matches!(sfx.state(h), PlaybackState::Stopped));
+
+        // steal the oldest voice rather than dropping the new sound
+        if playing.len() >= event.effect.def().max_voices {
+            let oldest = playing.remove(0);
+            if let Some(instance) = instances.get_mut(&oldest) {
+                instance.stop(AudioTween::linear(Duration::from_millis(30)));
+            }
+        }
+
+        let (mut command, _) = play(event.effect, &sfx, &asset_server, &settings);
+        if let Some(x) = event.x {
+            command.with_panning(panning(x, &wins));
+        }
+        playing.push(command.handle());
+    }
+}
+
+fn emitter_start(
+    mut voices: ResMut<Voices>,
+    mut emitters: Query<(Entity, &mut SfxEmitter), Added<SfxEmitter>>,
+    sfx: Res<AudioChannel<Sfx>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    for (e, mut emitter) in emitters.iter_mut() {
+        let looping = voices
+            .emitters
+            .values()
+            .filter(|(effect, _)| *effect == emitter.effect)
+            .count();
+        if looping >= emitter.effect.def().max_voices {
+            continue;
+        }
+
+        let (mut command, volume) = play(emitter.effect, &sfx, &asset_server, &settings);
+        emitter.volume = volume;
+        let handle = command.looped().with_volume(0.0).handle();
+        voices.emitters.insert(e, (emitter.effect, handle));
+    }
+}
+
+fn emitter_update(
+    voices: Res<Voices>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    emitters: Query<(Entity, &SfxEmitter, &GlobalTransform)>,
+    wins: Query<&Window, With<PrimaryWindow>>,
+) {
+    for (e, emitter, t) in emitters.iter() {
+        let Some(instance) = voices
+            .emitters
+            .get(&e)
+            .and_then(|(_, h)| instances.get_mut(h))
+        else {
+            continue;
+        };
+
+        let pan = panning(t.translation().x, &wins);
+        let falloff = 1.0 - (pan - 0.5).abs() * 2.0;
+        instance.set_panning(pan, AudioTween::default());
+        instance.set_volume(emitter.volume * falloff, AudioTween::default());
+    }
+}
+
+fn emitter_stop(
+    mut voices: ResMut<Voices>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    mut removed: RemovedComponents<SfxEmitter>,
+) {
+    for e in removed.iter() {
+        if let Some((_, handle)) = voices.emitters.remove(&e) {
+            if let Some(instance) = instances.get_mut(&handle) {
+                instance.stop(AudioTween::linear(Duration::from_millis(200)));
+            }
+        }
+    }
+}
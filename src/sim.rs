@@ -0,0 +1,568 @@
+//! The rules of a run with nothing drawn, shared with the leaderboard server
+//! so it can fly submitted replays through the same pillars as the game.
+//! Keep this to std, serde and rand so the server can build it on its own.
+
+use std::ops::Range;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Edges of the gap in a pillar's local space, before scaling.
+pub const GAP_TOP: f32 = 43.0;
+pub const GAP_BOTTOM: f32 = -50.0;
+/// Half a pillar's width, before scaling.
+pub const PILLAR_HALF_WIDTH: f32 = 24.0;
+pub const PILLAR_SCALE: f32 = 2.5;
+pub const BEE_HALF_SIZE: f32 = 35.0;
+/// Where the bee hovers about, and pillars past it have been scored.
+pub const BEE_X: f32 = 350.0;
+/// Pillar speed before the mode's multiplier.
+pub const BASE_SPEED: f32 = 150.0;
+/// Seconds between pillars.
+pub const SPAWN_EVERY: f32 = 2.5;
+/// Pillars between one boss and the next.
+pub const BOSS_EVERY: usize = 20;
+/// Seconds pillars hold off for while a boss is about.
+pub const BOSS_TIME: f32 = 15.0;
+/// Bottom of the screen, which counts as a crash.
+pub const FLOOR: f32 = -360.0;
+/// Seconds between recorded positions in a replay.
+pub const SAMPLE_EVERY: f32 = 0.05;
+/// Seconds in one step of the bee's flight, which is stepped at a fixed
+/// rate so the same flaps always fly the same way.
+pub const FLIGHT_STEP: f32 = 1.0 / 60.0;
+/// Speed a flap sets, in pixels per step.
+pub const FLAP_SPEED: f32 = 4.0;
+/// Speed lost each second, in pixels per step.
+pub const GRAVITY: f32 = 10.0;
+/// Furthest the world moves up or down under the bee.
+pub const WORLD_BOUND: f32 = 200.0;
+/// Highest the bee can fly in classic controls.
+pub const CEILING: f32 = 320.0;
+/// Box around `BEE_X` the bee wanders about in, when the world moves
+/// instead.
+pub const WANDER: (f32, f32) = (100.0, 220.0);
+/// Where pillars can come in, just off the left of the view. The right end
+/// is for the narrowest view the game shows, and the left for one four
+/// times as wide as it is tall.
+pub const SPAWN_X: (f32, f32) = (-1540.0, -740.0);
+
+/// Gap between weather events, and how long each one lasts.
+const CALM: Range<f32> = 12.0..25.0;
+const STORM: Range<f32> = 8.0..14.0;
+/// How quickly weather eases in and out, per second.
+const WEATHER_EASE: f32 = 0.5;
+/// Extra speed lost each second while it rains, as if the wings got wet.
+const RAIN_WEIGHT: f32 = 3.0;
+/// Most speed a gust of wind adds or takes away each second.
+const GUST_STRENGTH: f32 = 6.0;
+/// Seconds between gusts.
+const GUST_EVERY: f32 = 1.5;
+
+/// Pixels of doubt given to the bee when checking a replay, covering the
+/// frame or so a recorded position can lag behind its sample time.
+const SLACK: f32 = 8.0;
+/// Seconds either side of its time a sample may have been taken at, for
+/// slow frames.
+const LAG: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rules {
+    /// Multiplier on `BASE_SPEED`.
+    pub speed: f32,
+    /// Vertical scale of the pillars, squeezing the gap between them.
+    pub gap_scale: f32,
+    /// Seconds before the run ends on its own.
+    pub time_limit: Option<f32>,
+}
+
+pub const STANDARD: Rules = Rules {
+    speed: 1.0,
+    gap_scale: 1.0,
+    time_limit: None,
+};
+pub const TIME_ATTACK: Rules = Rules {
+    time_limit: Some(60.0),
+    ..STANDARD
+};
+pub const SUDDEN_DEATH: Rules = Rules {
+    speed: 1.5,
+    gap_scale: 0.8,
+    time_limit: None,
+};
+pub const DAILY: Rules = Rules {
+    speed: 1.2,
+    gap_scale: 0.9,
+    time_limit: None,
+};
+
+/// Rules of the modes with a leaderboard, by the name runs are submitted
+/// under.
+pub fn ranked(mode: &str) -> Option<Rules> {
+    match mode {
+        "Endless" => Some(STANDARD),
+        "TimeAttack" => Some(TIME_ATTACK),
+        "SuddenDeath" => Some(SUDDEN_DEATH),
+        "Daily" => Some(DAILY),
+        _ => None,
+    }
+}
+
+/// Leaderboard a run goes on. Each day's challenge gets its own.
+pub fn board(mode: &str, seed: u64) -> String {
+    match mode {
+        "Daily" => format!("Daily-{}", seed),
+        _ => mode.to_string(),
+    }
+}
+
+/// Heights of the pillar gaps, in the order they come.
+pub struct Layout(StdRng);
+
+impl Layout {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+
+    pub fn next_offset(&mut self) -> f32 {
+        (self.0.gen::<f32>() - 0.5) * 200.0
+    }
+}
+
+/// Seconds into a run the `i`th pillar comes in, counting from 0.
+pub fn spawn_time(i: usize) -> f32 {
+    i as f32 * SPAWN_EVERY + (i / BOSS_EVERY) as f32 * BOSS_TIME
+}
+
+/// The bee's climb and fall. `y` is the world's height under the bee, or
+/// the bee's own in classic controls.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Flight {
+    pub y: f32,
+    pub y_vel: f32,
+}
+
+impl Flight {
+    /// Moves on one `FLIGHT_STEP`, flapping first if `flap`, with `pull`
+    /// added to the speed by the weather.
+    pub fn step(&mut self, flap: bool, pull: f32, classic: bool) {
+        if flap {
+            self.y_vel = FLAP_SPEED;
+        }
+        self.y_vel += pull - GRAVITY * FLIGHT_STEP;
+        self.y += self.y_vel;
+        if !classic {
+            self.y = self.y.clamp(-WORLD_BOUND, WORLD_BOUND);
+        } else if self.y > CEILING {
+            self.y = CEILING;
+            self.y_vel = 0.0;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Wind,
+    Fog,
+}
+
+/// The weather through a run, drawn from its seed so a replay meets the
+/// same storms. Stepped along with the bee's flight.
+#[derive(Clone, Debug)]
+pub struct Weather {
+    rng: StdRng,
+    pub kind: WeatherKind,
+    /// Steps left until the weather turns.
+    turns_in: u32,
+    /// How far each kind has eased in, from 0 to 1.
+    pub rain: f32,
+    pub wind: f32,
+    pub fog: f32,
+    /// Direction and strength of the current gust, from -1 to 1.
+    gust: f32,
+    steps: u32,
+}
+
+impl Weather {
+    pub fn new(seed: u64) -> Self {
+        // a stream of its own, so the weather doesn't move the pillars
+        let mut rng = StdRng::seed_from_u64(seed ^ 0x5745_4154_4845_5221);
+        let turns_in = steps_in(&mut rng, CALM);
+        Self {
+            rng,
+            kind: WeatherKind::Clear,
+            turns_in,
+            rain: 0.0,
+            wind: 0.0,
+            fog: 0.0,
+            gust: 0.0,
+            steps: 0,
+        }
+    }
+
+    /// Moves on one `FLIGHT_STEP`, returning the speed it adds to the bee's
+    /// flight.
+    pub fn step(&mut self) -> f32 {
+        self.turns_in = self.turns_in.saturating_sub(1);
+        if self.turns_in == 0 {
+            let (kind, length) = match self.kind {
+                WeatherKind::Clear => {
+                    let kinds = [WeatherKind::Rain, WeatherKind::Wind, WeatherKind::Fog];
+                    (kinds[self.rng.gen_range(0..kinds.len())], STORM)
+                }
+                _ => (WeatherKind::Clear, CALM),
+            };
+            self.kind = kind;
+            self.turns_in = steps_in(&mut self.rng, length);
+        }
+
+        let ease = |current: f32, on: bool| {
+            let target = if on { 1.0 } else { 0.0 };
+            let most = WEATHER_EASE * FLIGHT_STEP;
+            current + (target - current).clamp(-most, most)
+        };
+        self.rain = ease(self.rain, self.kind == WeatherKind::Rain);
+        self.wind = ease(self.wind, self.kind == WeatherKind::Wind);
+        self.fog = ease(self.fog, self.kind == WeatherKind::Fog);
+
+        self.steps += 1;
+        if self
+            .steps
+            .is_multiple_of((GUST_EVERY / FLIGHT_STEP).round() as u32)
+        {
+            self.gust = self.rng.gen_range(-1.0..1.0);
+        }
+
+        (self.gust * GUST_STRENGTH * self.wind - RAIN_WEIGHT * self.rain) * FLIGHT_STEP
+    }
+}
+
+fn steps_in(rng: &mut StdRng, secs: Range<f32>) -> u32 {
+    (rng.gen_range(secs) / FLIGHT_STEP).round() as u32
+}
+
+/// Where the bee and its world were at one moment of a run.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Sample {
+    pub x: f32,
+    pub y: f32,
+    pub world: f32,
+    /// Score so far.
+    #[serde(default)]
+    pub score: u32,
+}
+
+impl Sample {
+    pub fn lerp(self, other: Sample, f: f32) -> Sample {
+        Sample {
+            x: self.x + (other.x - self.x) * f,
+            y: self.y + (other.y - self.y) * f,
+            world: self.world + (other.world - self.world) * f,
+            score: self.score,
+        }
+    }
+}
+
+/// A run sent to the leaderboard.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Submission {
+    pub name: String,
+    pub mode: String,
+    pub seed: u64,
+    pub score: u32,
+    /// Where pillars came in, which depends on the window's shape, within
+    /// `SPAWN_X`.
+    pub spawn_x: f32,
+    /// Flown with the bee moving instead of the world.
+    #[serde(default)]
+    pub classic: bool,
+    /// Seconds into the run each flap came at, on a `FLIGHT_STEP`.
+    pub flaps: Vec<f32>,
+    /// Positions every `SAMPLE_EVERY` seconds.
+    pub samples: Vec<Sample>,
+}
+
+impl Submission {
+    /// Height the flaps put the bee, or its world, at after each step.
+    fn flight(&self, steps: usize) -> Vec<f32> {
+        let mut flaps = self
+            .flaps
+            .iter()
+            .map(|at| (at / FLIGHT_STEP).round() as usize);
+        let mut next = flaps.next();
+        let mut flight = Flight::default();
+        let mut weather = Weather::new(self.seed);
+        let mut heights = vec![flight.y];
+        for step in 0..steps {
+            let mut flap = false;
+            while next.is_some_and(|at| at <= step) {
+                flap = true;
+                next = flaps.next();
+            }
+            flight.step(flap, weather.step(), self.classic);
+            heights.push(flight.y);
+        }
+        heights
+    }
+
+    /// Whether a sample is somewhere the flaps and the bee's wandering
+    /// could have put it.
+    fn follows(&self, t: f32, sample: &Sample, heights: &[f32]) -> bool {
+        let first = ((t - LAG) / FLIGHT_STEP).floor().max(0.0) as usize;
+        let last = (((t + LAG) / FLIGHT_STEP).ceil() as usize).min(heights.len() - 1);
+        let (low, high) = heights[first..=last]
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(low, high), &y| {
+                (low.min(y), high.max(y))
+            });
+
+        let (y, wandered) = match self.classic {
+            // the world stays put
+            true => (
+                sample.y,
+                (sample.x - BEE_X).abs() <= SLACK && sample.world.abs() <= SLACK,
+            ),
+            false => (
+                sample.world,
+                (sample.x - BEE_X).abs() <= WANDER.0 / 2.0 + SLACK
+                    && sample.y.abs() <= WANDER.1 / 2.0 + SLACK,
+            ),
+        };
+        wandered && y >= low - SLACK && y <= high + SLACK
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+}
+
+/// What the server made of a submission.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Verdict {
+    /// On the board, counting from 1.
+    Ranked(usize),
+    /// Verified, but not good enough for the board.
+    Unranked,
+    Rejected(String),
+}
+
+/// The server's answer to a submission, with the board as it now stands.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Receipt {
+    pub verdict: Verdict,
+    pub top: Vec<Entry>,
+}
+
+/// Flies a replay through the pillars its seed lays out, returning the
+/// score it really earned before its first crash. Bosses aren't flown
+/// against, only the pillars either side of them.
+///
+/// The flaps are flown again under the same rules as the game, and the run
+/// counts only for as long as its samples follow them. A bee knocked out of
+/// the sky stops following them too, which is fine as it's done scoring.
+pub fn verify(run: &Submission) -> Result<u32, String> {
+    let rules = ranked(&run.mode).ok_or_else(|| format!("{} has no leaderboard", run.mode))?;
+    let x_vel = BASE_SPEED * rules.speed;
+
+    let length = run.samples.len() as f32 * SAMPLE_EVERY;
+    if let Some(limit) = rules.time_limit {
+        if length > limit + 1.0 {
+            return Err(format!("ran {:.1}s of a {}s limit", length, limit));
+        }
+    }
+    if !(SPAWN_X.0..=SPAWN_X.1).contains(&run.spawn_x) {
+        return Err(format!("pillars can't come in at x {}", run.spawn_x));
+    }
+    if run.flaps.windows(2).any(|w| w[0] > w[1]) {
+        return Err("flaps out of order".to_string());
+    }
+    let heights = run.flight(((length + LAG) / FLIGHT_STEP).ceil() as usize);
+
+    let half_height = PILLAR_SCALE * rules.gap_scale;
+    let reach = PILLAR_HALF_WIDTH * PILLAR_SCALE + BEE_HALF_SIZE - SLACK;
+    let bee = BEE_HALF_SIZE - SLACK;
+
+    let mut layout = Layout::new(run.seed);
+    // when each pillar came in, and its gap's offset
+    let mut pillars: Vec<(f32, f32)> = Vec::new();
+    let mut score = 0;
+
+    for (i, sample) in run.samples.iter().enumerate() {
+        let t = i as f32 * SAMPLE_EVERY;
+        while spawn_time(pillars.len()) <= t {
+            pillars.push((spawn_time(pillars.len()), layout.next_offset()));
+        }
+
+        if sample.y < FLOOR || !run.follows(t, sample, &heights) {
+            return Ok(score);
+        }
+
+        let mut passed = 0;
+        for &(at, offset) in &pillars {
+            let x = run.spawn_x + (t - at) * x_vel;
+            let y = sample.world + offset;
+            if (x - sample.x).abs() < reach
+                && (sample.y + bee > y + GAP_TOP * half_height
+                    || sample.y - bee < y + GAP_BOTTOM * half_height)
+            {
+                return Ok(score);
+            }
+            if x > BEE_X {
+                passed += 1;
+            }
+        }
+        score = passed;
+    }
+
+    Ok(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flies an honest run, flapping whenever the bee sinks below the gap of
+    /// the next pillar it has to get through.
+    fn flown(seed: u64, secs: f32, classic: bool) -> Submission {
+        let spawn_x = SPAWN_X.1;
+        let mut layout = Layout::new(seed);
+        let offsets: Vec<f32> = (0..20).map(|_| layout.next_offset()).collect();
+
+        let mut flight = Flight::default();
+        let mut weather = Weather::new(seed);
+        let mut flaps = Vec::new();
+        let mut samples = Vec::new();
+        let steps = (secs / FLIGHT_STEP) as usize;
+        for step in 0..steps {
+            let t = step as f32 * FLIGHT_STEP;
+            if samples.len() as f32 * SAMPLE_EVERY <= t {
+                samples.push(match classic {
+                    true => Sample {
+                        x: BEE_X,
+                        y: flight.y,
+                        world: 0.0,
+                        score: 0,
+                    },
+                    false => Sample {
+                        x: BEE_X,
+                        y: 0.0,
+                        world: flight.y,
+                        score: 0,
+                    },
+                });
+            }
+
+            let next = (0..)
+                .find(|&i| spawn_x + (t - spawn_time(i)) * BASE_SPEED < BEE_X + 100.0)
+                .unwrap();
+            let middle = offsets[next] + (GAP_TOP + GAP_BOTTOM) / 2.0 * PILLAR_SCALE;
+            // the world moves the other way to the bee
+            let target = match classic {
+                true => middle,
+                false => -middle,
+            };
+            let flap = flight.y < target - 20.0 && flight.y_vel < 0.0;
+            if flap {
+                flaps.push(t);
+            }
+            flight.step(flap, weather.step(), classic);
+        }
+
+        Submission {
+            name: "Bee".to_string(),
+            mode: "Endless".to_string(),
+            seed,
+            score: 0,
+            spawn_x,
+            classic,
+            flaps,
+            samples,
+        }
+    }
+
+    #[test]
+    fn flap_rises_then_falls() {
+        let mut flight = Flight::default();
+        flight.step(true, 0.0, true);
+        assert!(flight.y > 0.0);
+        for _ in 0..120 {
+            flight.step(false, 0.0, true);
+        }
+        assert!(flight.y < 0.0);
+    }
+
+    #[test]
+    fn world_stays_in_bounds() {
+        let mut flight = Flight::default();
+        for _ in 0..600 {
+            flight.step(false, 0.0, false);
+        }
+        assert_eq!(flight.y, -WORLD_BOUND);
+    }
+
+    #[test]
+    fn honest_runs_score() {
+        for classic in [true, false] {
+            let run = flown(7, 20.0, classic);
+            assert_eq!(verify(&run), Ok(6), "classic: {}", classic);
+        }
+    }
+
+    #[test]
+    fn samples_must_follow_flaps() {
+        let mut run = flown(7, 20.0, false);
+        // lift the world into the gaps with no flaps to show for it
+        for sample in &mut run.samples[200..] {
+            sample.world += 40.0;
+        }
+        assert_eq!(verify(&run), Ok(2));
+    }
+
+    #[test]
+    fn dropped_flaps_end_the_run() {
+        let mut run = flown(7, 20.0, true);
+        run.flaps.retain(|&at| at < 12.0);
+        assert!(verify(&run).unwrap() < 6);
+    }
+
+    #[test]
+    fn forged_spawn_x_is_rejected() {
+        // pillars coming in right of the bee would all count as passed
+        let mut run = flown(7, 20.0, true);
+        run.spawn_x = 10000.0;
+        assert!(verify(&run).is_err());
+    }
+
+    #[test]
+    fn classic_world_stays_put() {
+        let mut run = flown(7, 20.0, true);
+        // move the world into the gaps rather than flying the bee there
+        for sample in &mut run.samples[200..] {
+            sample.world += 40.0;
+        }
+        assert_eq!(verify(&run), Ok(2));
+    }
+
+    #[test]
+    fn weather_follows_the_seed() {
+        let pulls = |seed| {
+            let mut weather = Weather::new(seed);
+            (0..7200).map(|_| weather.step()).collect::<Vec<_>>()
+        };
+        // two minutes in, there's been rain or wind to fly through
+        assert!(pulls(7).iter().any(|&pull| pull != 0.0));
+        assert_eq!(pulls(7), pulls(7));
+        assert_ne!(pulls(7), pulls(8));
+    }
+
+    #[test]
+    fn unranked_modes_are_rejected() {
+        let mut run = flown(7, 5.0, true);
+        run.mode = "Zen".to_string();
+        assert!(verify(&run).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::{BTreeSet, HashMap};
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    stats::{Achievement, Stats},
+    storage, GameState,
+};
+
+const WARDROBE_FILE: &str = "skins.ron";
+/// Size of one frame of a bee.
+const FRAME: f32 = 32.0;
+
+pub struct SkinsPlugin;
+
+impl Plugin for SkinsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Wardrobe::load())
+            .init_resource::<SkinAtlases>()
+            .add_system(build_atlases.in_schedule(OnExit(GameState::Loading)))
+            .add_system(dress.in_base_set(CoreSet::PostUpdate));
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum Skin {
+    #[default]
+    Classic,
+    Sketch,
+    Rose,
+    Golden,
+    Midnight,
+}
+
+/// What it takes to wear a skin.
+pub enum Unlock {
+    Free,
+    Achievement(Achievement),
+    /// Bought once in the shop with pollen collected in levels.
+    Pollen(u32),
+}
+
+impl Skin {
+    pub const ALL: [Skin; 5] = [
+        Skin::Classic,
+        Skin::Sketch,
+        Skin::Rose,
+        Skin::Golden,
+        Skin::Midnight,
+    ];
+
+    /// English name, to be passed through `Language::tr`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Skin::Classic => "Classic",
+            Skin::Sketch => "Sketch",
+            Skin::Rose => "Rose",
+            Skin::Golden => "Golden",
+            Skin::Midnight => "Midnight",
+        }
+    }
+
+    /// The bee's two frames, either side by side in one sheet or a
+    /// texture each.
+    pub fn textures(self) -> &'static [&'static str] {
+        match self {
+            Skin::Classic | Skin::Golden | Skin::Midnight => &["textures/bee.png"],
+            Skin::Sketch | Skin::Rose => &["textures/bee1.png", "textures/bee2.png"],
+        }
+    }
+
+    fn tint(self) -> Color {
+        match self {
+            Skin::Classic | Skin::Sketch => Color::WHITE,
+            Skin::Rose => Color::rgb(1.0, 0.6, 0.75),
+            // over 1.0 to catch the bloom
+            Skin::Golden => Color::rgb(1.6, 1.3, 0.4),
+            Skin::Midnight => Color::rgb(0.45, 0.5, 1.0),
+        }
+    }
+
+    pub fn unlock(self) -> Unlock {
+        match self {
+            Skin::Classic => Unlock::Free,
+            Skin::Sketch => Unlock::Achievement(Achievement::FirstFlight),
+            Skin::Rose => Unlock::Pollen(10),
+            Skin::Golden => Unlock::Pollen(25),
+            Skin::Midnight => Unlock::Achievement(Achievement::HiveMind),
+        }
+    }
+}
+
+/// Skins bought in the shop, and the one being worn.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Wardrobe {
+    worn: Skin,
+    bought: BTreeSet<Skin>,
+    /// Pollen spent in the shop so far.
+    spent: u32,
+}
+
+impl Wardrobe {
+    pub fn load() -> Self {
+        storage::load(WARDROBE_FILE)
+    }
+
+    pub fn save(&self) {
+        storage::save(WARDROBE_FILE, self);
+    }
+
+    pub fn worn(&self) -> Skin {
+        self.worn
+    }
+
+    pub fn owns(&self, skin: Skin, stats: &Stats) -> bool {
+        match skin.unlock() {
+            Unlock::Free => true,
+            Unlock::Achievement(achievement) => stats.unlocked(achievement),
+            Unlock::Pollen(_) => self.bought.contains(&skin),
+        }
+    }
+
+    /// Pollen left to spend.
+    pub fn pollen(&self, stats: &Stats) -> u32 {
+        stats.pollen().saturating_sub(self.spent)
+    }
+
+    /// Puts `skin` on, buying it first if it's for sale and there's the
+    /// pollen for it.
+    pub fn pick(&mut self, skin: Skin, stats: &Stats) {
+        if !self.owns(skin, stats) {
+            let Unlock::Pollen(price) = skin.unlock() else {
+                return;
+            };
+            if self.pollen(stats) < price {
+                return;
+            }
+            self.spent += price;
+            self.bought.insert(skin);
+        }
+        self.worn = skin;
+        self.save();
+    }
+}
+
+/// An atlas per skin, built once everything has loaded.
+#[derive(Resource, Default)]
+struct SkinAtlases(HashMap<Skin, Handle<TextureAtlas>>);
+
+/// Lays same sized frames side by side in one image.
+fn stitch(frames: &[&Image]) -> Option<Image> {
+    let first = frames.first()?;
+    let descriptor = &first.texture_descriptor;
+    if frames.iter().any(|f| {
+        f.texture_descriptor.size != descriptor.size
+            || f.texture_descriptor.format != descriptor.format
+    }) {
+        return None;
+    }
+
+    let height = descriptor.size.height as usize;
+    let row = first.data.len() / height;
+    let mut data = Vec::with_capacity(first.data.len() * frames.len());
+    for y in 0..height {
+        for frame in frames {
+            data.extend_from_slice(&frame.data[y * row..(y + 1) * row]);
+        }
+    }
+    Some(Image::new(
+        Extent3d {
+            width: descriptor.size.width * frames.len() as u32,
+            ..descriptor.size
+        },
+        TextureDimension::D2,
+        data,
+        descriptor.format,
+    ))
+}
+
+fn build_atlases(
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut skin_atlases: ResMut<SkinAtlases>,
+) {
+    for skin in Skin::ALL {
+        let atlas = match skin.textures() {
+            [sheet] => TextureAtlas::from_grid(
+                asset_server.load(*sheet),
+                Vec2::splat(FRAME),
+                2,
+                1,
+                Some(Vec2::new(1.0, 0.0)),
+                None,
+            ),
+            textures => {
+                let frames: Option<Vec<&Image>> = textures
+                    .iter()
+                    .map(|path| images.get(&asset_server.load(*path)))
+                    .collect();
+                // missing textures are already reported on the loading screen
+                let Some(image) = frames.and_then(|frames| stitch(&frames)) else {
+                    continue;
+                };
+                TextureAtlas::from_grid(
+                    images.add(image),
+                    Vec2::splat(FRAME),
+                    textures.len(),
+                    1,
+                    None,
+                    None,
+                )
+            }
+        };
+        skin_atlases.0.insert(skin, texture_atlases.add(atlas));
+    }
+}
+
+/// Bees wearing the player's skin, which changes with it.
+#[derive(Component)]
+pub struct Dressed;
+
+fn dress(
+    wardrobe: Res<Wardrobe>,
+    skin_atlases: Res<SkinAtlases>,
+    mut bees: Query<(
+        Ref<Dressed>,
+        &mut Handle<TextureAtlas>,
+        &mut TextureAtlasSprite,
+    )>,
+) {
+    let skin = wardrobe.worn();
+    let Some(atlas) = skin_atlases
+        .0
+        .get(&skin)
+        .or_else(|| skin_atlases.0.get(&Skin::Classic))
+    else {
+        return;
+    };
+
+    for (dressed, mut handle, mut sprite) in bees.iter_mut() {
+        if dressed.is_added() || wardrobe.is_changed() {
+            *handle = atlas.clone();
+            // keeping any transparency, as on the ghost
+            sprite.color = skin.tint().with_a(sprite.color.a());
+        }
+    }
+}
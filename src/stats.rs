@@ -0,0 +1,330 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bee_game::GameInfo,
+    levels::{ObstacleKind, Playtest},
+    settings::{Language, Settings},
+    storage, GameState,
+};
+
+const STATS_FILE: &str = "stats.ron";
+/// Seconds an achievement toast stays up.
+const TOAST_TIME: f32 = 3.0;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Stats::load())
+            .add_event::<Tally>()
+            .add_system(start_run.in_schedule(OnEnter(GameState::Game)))
+            .add_system(track.in_set(OnUpdate(GameState::Game)))
+            .add_system(end_run.in_schedule(OnExit(GameState::Game)))
+            .add_system(toasts);
+    }
+}
+
+/// Something worth counting, sent by the game as it happens to the first
+/// player's bee.
+pub enum Tally {
+    Flap,
+    Pillar,
+    NearMiss,
+    Pollen,
+    Crash(Hazard),
+}
+
+/// What a bee crashed into.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hazard {
+    Floor,
+    Pillar(ObstacleKind),
+    Boss,
+}
+
+impl Hazard {
+    /// English name, to be passed through `Language::tr`.
+    fn name(self) -> &'static str {
+        match self {
+            Hazard::Floor => "Floor",
+            Hazard::Pillar(ObstacleKind::Pipe) => "Pipe",
+            Hazard::Pillar(ObstacleKind::Narrow) => "Narrow pipe",
+            Hazard::Pillar(ObstacleKind::Wide) => "Wide pipe",
+            Hazard::Boss => "Boss",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Achievement {
+    FirstFlight,
+    GettingGoing,
+    HiveMind,
+    Glider,
+    CloseShave,
+    Marathon,
+    Pollinator,
+    Bouncy,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 8] = [
+        Achievement::FirstFlight,
+        Achievement::GettingGoing,
+        Achievement::HiveMind,
+        Achievement::Glider,
+        Achievement::CloseShave,
+        Achievement::Marathon,
+        Achievement::Pollinator,
+        Achievement::Bouncy,
+    ];
+
+    /// English name, to be passed through `Language::tr`.
+    pub fn name(self) -> &'static str {
+        use Achievement::*;
+        match self {
+            FirstFlight => "First Flight",
+            GettingGoing => "Getting Going",
+            HiveMind => "Hive Mind",
+            Glider => "Glider",
+            CloseShave => "Close Shave",
+            Marathon => "Marathon",
+            Pollinator => "Pollinator",
+            Bouncy => "Bouncy",
+        }
+    }
+
+    /// English description, to be passed through `Language::tr`.
+    pub fn description(self) -> &'static str {
+        use Achievement::*;
+        match self {
+            FirstFlight => "Pass a pillar",
+            GettingGoing => "Pass 10 pillars in a run",
+            HiveMind => "Pass 50 pillars in a run",
+            Glider => "Pass 10 pillars, then go 2 s without flapping",
+            CloseShave => "Scrape past 5 pillars in a run",
+            Marathon => "Fly for 2 minutes in a run",
+            Pollinator => "Collect 25 pollen",
+            Bouncy => "Bounce off 20 pillars in a run",
+        }
+    }
+}
+
+/// Lifetime totals, and the achievements unlocked so far.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Stats {
+    runs: u32,
+    flaps: u32,
+    pillars: u32,
+    pollen: u32,
+    deaths: BTreeMap<Hazard, u32>,
+    /// Seconds.
+    longest_run: f32,
+    unlocked: BTreeSet<Achievement>,
+}
+
+impl Stats {
+    pub fn load() -> Self {
+        storage::load(STATS_FILE)
+    }
+
+    pub fn save(&self) {
+        storage::save(STATS_FILE, self);
+    }
+
+    /// Collected over every run, spent or not.
+    pub fn pollen(&self) -> u32 {
+        self.pollen
+    }
+
+    pub fn unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// The totals, a line each.
+    pub fn summary(&self, lang: Language) -> String {
+        let mut deaths = String::new();
+        for (hazard, count) in &self.deaths {
+            deaths += &format!("  {} {}", lang.tr(hazard.name()), count);
+        }
+        format!(
+            "{}  {}\n{}  {}\n{}  {}\n{}  {}\n{}  {:.1}s\n{}{}",
+            lang.tr("Runs"),
+            self.runs,
+            lang.tr("Flaps"),
+            self.flaps,
+            lang.tr("Pillars"),
+            self.pillars,
+            lang.tr("Pollen"),
+            self.pollen,
+            lang.tr("Longest run"),
+            self.longest_run,
+            lang.tr("Deaths"),
+            deaths,
+        )
+    }
+}
+
+/// Present during a run that counts towards the stats, which playtests
+/// from the editor don't.
+#[derive(Resource, Default)]
+struct RunTally {
+    /// Seconds alive.
+    elapsed: f32,
+    since_flap: f32,
+    near_misses: u32,
+}
+
+fn start_run(mut commands: Commands, playtest: Option<Res<Playtest>>, mut stats: ResMut<Stats>) {
+    commands.remove_resource::<RunTally>();
+    if playtest.is_some() {
+        return;
+    }
+    stats.runs += 1;
+    commands.init_resource::<RunTally>();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn track(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut tallies: EventReader<Tally>,
+    run: Option<ResMut<RunTally>>,
+    mut stats: ResMut<Stats>,
+    game_info: Res<GameInfo>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    toasts: Query<(), With<Toast>>,
+) {
+    let Some(mut run) = run else {
+        tallies.clear();
+        return;
+    };
+
+    if !game_info.is_dead() {
+        run.elapsed += time.delta_seconds();
+        run.since_flap += time.delta_seconds();
+    }
+    for tally in tallies.iter() {
+        match tally {
+            Tally::Flap => {
+                stats.flaps += 1;
+                run.since_flap = 0.0;
+            }
+            Tally::Pillar => stats.pillars += 1,
+            Tally::NearMiss => run.near_misses += 1,
+            Tally::Pollen => stats.pollen += 1,
+            Tally::Crash(hazard) => *stats.deaths.entry(*hazard).or_default() += 1,
+        }
+    }
+
+    let score = game_info.score;
+    let earned = Achievement::ALL.into_iter().filter(|a| {
+        !stats.unlocked(*a)
+            && match a {
+                Achievement::FirstFlight => score >= 1,
+                Achievement::GettingGoing => score >= 10,
+                Achievement::HiveMind => score >= 50,
+                Achievement::Glider => score >= 10 && run.since_flap >= 2.0 && !game_info.is_dead(),
+                Achievement::CloseShave => run.near_misses >= 5,
+                Achievement::Marathon => run.elapsed >= 120.0,
+                Achievement::Pollinator => stats.pollen >= 25,
+                Achievement::Bouncy => game_info.bounces >= 20,
+            }
+    });
+    let earned: Vec<Achievement> = earned.collect();
+    if earned.is_empty() {
+        return;
+    }
+
+    let showing = toasts.iter().count();
+    for (i, achievement) in earned.into_iter().enumerate() {
+        stats.unlocked.insert(achievement);
+        spawn_toast(
+            &mut commands,
+            &asset_server,
+            &settings,
+            achievement,
+            showing + i,
+        );
+    }
+    stats.save();
+}
+
+fn end_run(run: Option<Res<RunTally>>, mut stats: ResMut<Stats>) {
+    let Some(run) = run else {
+        return;
+    };
+    stats.longest_run = stats.longest_run.max(run.elapsed);
+    stats.save();
+}
+
+#[derive(Component)]
+struct Toast(Timer);
+
+/// Pops up along the bottom of the screen, above any already showing.
+fn spawn_toast(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    settings: &Settings,
+    achievement: Achievement,
+    shown: usize,
+) {
+    let lang = settings.language;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(30.0 + 60.0 * shown as f32),
+                        left: Val::Px(0.0),
+                        ..default()
+                    },
+                    size: Size::width(Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            Toast(Timer::from_seconds(TOAST_TIME, TimerMode::Once)),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: settings.palette.button_normal().into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        format!(
+                            "{}: {}",
+                            lang.tr("Achievement unlocked"),
+                            lang.tr(achievement.name())
+                        ),
+                        TextStyle {
+                            font: asset_server.load("fonts/goodtimes.otf"),
+                            font_size: 24.0,
+                            color: settings.palette.button_text(),
+                        },
+                    ));
+                });
+        });
+}
+
+/// Toasts outlive the run they were earned in, so they tidy themselves up.
+fn toasts(mut commands: Commands, mut query: Query<(Entity, &mut Toast)>, time: Res<Time>) {
+    for (e, mut toast) in query.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(e).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Directory the game keeps its config and save files in.
+pub fn data_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    base.join("beescape")
+}
+
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    let path = data_dir().join(name);
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return T::default();
+    };
+
+    match ron::from_str(&contents) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("ignoring unreadable {}: {}", path.display(), e);
+            T::default()
+        }
+    }
+}
+
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let dir = data_dir();
+    let contents = match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => contents,
+        Err(e) => {
+            warn!("could not serialize {}: {}", name, e);
+            return;
+        }
+    };
+
+    if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join(name), contents)) {
+        warn!("could not write {}: {}", dir.join(name).display(), e);
+    }
+}
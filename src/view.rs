@@ -0,0 +1,62 @@
+use bevy::{
+    prelude::*,
+    render::camera::ScalingMode,
+    window::{PrimaryWindow, WindowResized},
+};
+
+/// The world is laid out for this size; wider or taller windows see more of
+/// it rather than stretching it.
+pub const VIRTUAL_WIDTH: f32 = 1280.0;
+pub const VIRTUAL_HEIGHT: f32 = 720.0;
+
+pub struct ViewPlugin;
+
+impl Plugin for ViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(scale_ui);
+    }
+}
+
+pub fn projection() -> OrthographicProjection {
+    OrthographicProjection {
+        far: 1000.0,
+        scaling_mode: ScalingMode::AutoMin {
+            min_width: VIRTUAL_WIDTH,
+            min_height: VIRTUAL_HEIGHT,
+        },
+        ..default()
+    }
+}
+
+/// Size of the world area the cameras show in the given window.
+pub fn visible_size(window: &Window) -> Vec2 {
+    let aspect = window.width() / window.height();
+    if aspect > VIRTUAL_WIDTH / VIRTUAL_HEIGHT {
+        Vec2::new(VIRTUAL_HEIGHT * aspect, VIRTUAL_HEIGHT)
+    } else {
+        Vec2::new(VIRTUAL_WIDTH, VIRTUAL_WIDTH / aspect)
+    }
+}
+
+/// Like `visible_size` for the primary window, falling back to the virtual
+/// resolution when there isn't one.
+pub fn primary_visible_size(wins: &Query<&Window, With<PrimaryWindow>>) -> Vec2 {
+    wins.get_single()
+        .map(visible_size)
+        .unwrap_or(Vec2::new(VIRTUAL_WIDTH, VIRTUAL_HEIGHT))
+}
+
+fn scale_ui(
+    mut events: EventReader<WindowResized>,
+    wins: Query<&Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+
+    if let Ok(window) = wins.get_single() {
+        let scale = (window.width() / VIRTUAL_WIDTH).min(window.height() / VIRTUAL_HEIGHT);
+        ui_scale.scale = scale as f64;
+    }
+}